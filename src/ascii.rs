@@ -0,0 +1,128 @@
+use core::fmt;
+
+use crate::Error;
+
+/// A byte that is guaranteed to be ASCII, i.e. in the range `0x00..=0x7f`
+///
+/// Any sequence of ASCII bytes is valid UTF-8, so a string can be edited as a slice of
+/// `AsciiByte` without checking the result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[repr(transparent)]
+pub struct AsciiByte(u8);
+
+impl AsciiByte {
+    /// Create an `AsciiByte`, or `None` if `byte` is not ASCII.
+    pub const fn new(byte: u8) -> Option<Self> {
+        if byte.is_ascii() {
+            Some(Self(byte))
+        } else {
+            None
+        }
+    }
+
+    /// The byte value.
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+
+    /// The byte as a `char`.
+    pub const fn as_char(self) -> char {
+        self.0 as char
+    }
+
+    /// The same byte converted to ASCII upper case.
+    pub const fn to_ascii_uppercase(self) -> Self {
+        Self(self.0.to_ascii_uppercase())
+    }
+
+    /// The same byte converted to ASCII lower case.
+    pub const fn to_ascii_lowercase(self) -> Self {
+        Self(self.0.to_ascii_lowercase())
+    }
+}
+
+impl TryFrom<u8> for AsciiByte {
+    type Error = u8;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        Self::new(byte).ok_or(byte)
+    }
+}
+
+impl TryFrom<char> for AsciiByte {
+    type Error = char;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        if c.is_ascii() {
+            Ok(Self(c as u8))
+        } else {
+            Err(c)
+        }
+    }
+}
+
+impl From<AsciiByte> for u8 {
+    fn from(byte: AsciiByte) -> Self {
+        byte.0
+    }
+}
+
+impl From<AsciiByte> for char {
+    fn from(byte: AsciiByte) -> Self {
+        byte.as_char()
+    }
+}
+
+impl PartialEq<u8> for AsciiByte {
+    fn eq(&self, other: &u8) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Display for AsciiByte {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.as_char(), f)
+    }
+}
+
+/// View the bytes of an ASCII string as `AsciiByte`s, or fail if it contains any other bytes.
+pub(crate) fn ascii_bytes_mut(s: &mut str) -> Result<&mut [AsciiByte], Error> {
+    if let Some(index) = s.bytes().position(|b| !b.is_ascii()) {
+        return Err(Error::NotAscii { index });
+    }
+    // SAFETY: `AsciiByte` is a transparent wrapper around `u8`, every byte of the string has
+    // just been checked to be ASCII, and only ASCII bytes can be written through the result,
+    // so the string remains valid UTF-8
+    Ok(unsafe { &mut *(s.as_bytes_mut() as *mut [u8] as *mut [AsciiByte]) })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::WithCheckedBytes;
+
+    use super::*;
+
+    #[test]
+    fn edit_ascii() {
+        let mut my_string = "hello".to_owned();
+        my_string.with_ascii_bytes_mut(|s| {
+            s[0] = s[0].to_ascii_uppercase();
+            s[4] = AsciiByte::try_from('!').unwrap();
+        }).unwrap();
+        assert_eq!(my_string, "Hell!");
+    }
+
+    #[test]
+    fn reject_non_ascii() {
+        let mut my_string = "héllo".to_owned();
+        let err = my_string.with_ascii_bytes_mut(|_| unreachable!()).unwrap_err();
+        assert!(matches!(err, Error::NotAscii { index: 1 }));
+    }
+
+    #[test]
+    fn construction() {
+        assert_eq!(AsciiByte::new(b'a').map(AsciiByte::get), Some(b'a'));
+        assert_eq!(AsciiByte::new(0x80), None);
+        assert_eq!(AsciiByte::try_from('é'), Err('é'));
+    }
+}
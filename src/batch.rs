@@ -0,0 +1,121 @@
+use alloc::vec::Vec;
+
+use crate::{BatchError, MutableStringBytes};
+
+/// Extension trait for editing several strings as bytes, all or nothing
+pub trait WithCheckedBatch {
+    /// Edit a group of strings as if they were byte arrays, committing either all of the
+    /// changes or none of them.
+    ///
+    /// The closure receives one [`MutableStringBytes`] view per string, in order. When it
+    /// returns, every view is checked. If any of them does not contain valid UTF-8, none of
+    /// the strings are modified and the error lists each one that failed. Otherwise all of
+    /// the strings are updated and the closure's return value is passed back.
+    fn with_checked_batch_mut<R, F>(&mut self, f: F) -> Result<R, BatchError>
+    where
+        F: FnOnce(&mut [MutableStringBytes<'_>]) -> R;
+}
+
+/// Run `f` over a view of each target, then commit all of them if they are all valid.
+fn edit_all<'a, R, F>(targets: impl IntoIterator<Item = &'a mut str>, f: F) -> Result<R, BatchError>
+where
+    F: FnOnce(&mut [MutableStringBytes<'_>]) -> R,
+{
+    let mut views: Vec<_> = targets.into_iter().map(MutableStringBytes::new).collect();
+    let res = f(&mut views);
+    let failures: Vec<_> = views
+        .iter()
+        .enumerate()
+        .filter_map(|(index, view)| view.check().err().map(|e| (index, e)))
+        .collect();
+    if !failures.is_empty() {
+        return Err(BatchError::new(failures));
+    }
+    for view in &mut views {
+        view.commit().expect("views were checked before committing");
+    }
+    Ok(res)
+}
+
+impl<T: AsMut<str>> WithCheckedBatch for [T] {
+    fn with_checked_batch_mut<R, F>(&mut self, f: F) -> Result<R, BatchError>
+    where
+        F: FnOnce(&mut [MutableStringBytes<'_>]) -> R,
+    {
+        edit_all(self.iter_mut().map(AsMut::as_mut), f)
+    }
+}
+
+impl<T: AsMut<str>> WithCheckedBatch for Vec<T> {
+    fn with_checked_batch_mut<R, F>(&mut self, f: F) -> Result<R, BatchError>
+    where
+        F: FnOnce(&mut [MutableStringBytes<'_>]) -> R,
+    {
+        self.as_mut_slice().with_checked_batch_mut(f)
+    }
+}
+
+macro_rules! impl_for_tuple {
+    ($($name:ident $index:tt),+) => {
+        impl<$($name: AsMut<str>),+> WithCheckedBatch for ($($name,)+) {
+            fn with_checked_batch_mut<R, F>(&mut self, f: F) -> Result<R, BatchError>
+            where
+                F: FnOnce(&mut [MutableStringBytes<'_>]) -> R,
+            {
+                edit_all([$(self.$index.as_mut()),+], f)
+            }
+        }
+    };
+}
+
+impl_for_tuple!(T0 0, T1 1);
+impl_for_tuple!(T0 0, T1 1, T2 2);
+impl_for_tuple!(T0 0, T1 1, T2 2, T3 3);
+impl_for_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4);
+impl_for_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5);
+impl_for_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6);
+impl_for_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7);
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+
+    use super::*;
+
+    #[test]
+    fn all_committed() {
+        let mut fields = vec![String::from("Hello"), String::from("world")];
+        fields.with_checked_batch_mut(|views| {
+            views[0].set(0, b'J');
+            views[1].set(0, b'W');
+        }).unwrap();
+        assert_eq!(fields, ["Jello", "World"]);
+    }
+
+    #[test]
+    fn none_committed() {
+        let mut fields = vec![String::from("a"), String::from("b"), String::from("c")];
+        let err = fields.with_checked_batch_mut(|views| {
+            for view in views.iter_mut() {
+                view.set(0, b'x');
+            }
+            views[2].set(0, 0xff);
+            views[0].set(0, 0xfe);
+        }).unwrap_err();
+        assert_eq!(err.failed_indices().collect::<Vec<_>>(), [0, 2]);
+        assert_eq!(err.failures()[1].1.as_bytes(), b"\xff");
+        assert_eq!(fields, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn tuple_of_strs() {
+        let mut name = String::from("ann");
+        let mut city = String::from("oslo");
+        (name.as_mut_str(), city.as_mut_str()).with_checked_batch_mut(|views| {
+            for view in views {
+                view.set(0, view[0].to_ascii_uppercase());
+            }
+        }).unwrap();
+        assert_eq!((name.as_str(), city.as_str()), ("Ann", "Oslo"));
+    }
+}
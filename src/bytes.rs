@@ -0,0 +1,561 @@
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+use core::ops::{Bound, Deref, DerefMut, Range, RangeBounds};
+use core::str::Utf8Error;
+
+#[cfg(feature = "alloc")]
+use crate::journal::{InPlace, Journal};
+#[cfg(feature = "alloc")]
+use crate::ChangeSet;
+use crate::{validate, Error, InvalidUtf8Error};
+
+/// Mutable view into a string's content expressed as bytes
+///
+/// The original string is only copied the first time the view is mutated, either to the heap
+/// or to a scratch buffer supplied by the caller. Writes made
+/// through [`set`](Self::set), [`write_at`](Self::write_at) and [`range_mut`](Self::range_mut)
+/// are tracked so that only those regions need to be checked when the edit is committed.
+/// Writes made through `DerefMut` (for example `s[3] = b'x'`) could touch any byte, so the
+/// commit compares the whole buffer against the original to find what changed.
+///
+/// When editing in place, the view writes directly into the string and keeps a journal of
+/// the bytes it overwrote instead. Mutating through `DerefMut` saves a copy of the whole
+/// string to the journal, so the tracked methods are preferable there too.
+pub struct MutableStringBytes<'a> {
+    storage: Storage<'a>,
+}
+
+enum Storage<'a> {
+    Copy(CopyOnWrite<'a>),
+    #[cfg(feature = "alloc")]
+    InPlace(InPlace<'a>),
+}
+
+/// A position in an edit session that later changes can be rolled back to
+///
+/// See [`MutableStringBytes::savepoint`].
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Savepoint {
+    mark: usize,
+    /// Identifies the savepoint to the journal, which knows whether it is still valid
+    generation: u64,
+    copied: bool,
+}
+
+/// A string being edited through a copy of its bytes
+struct CopyOnWrite<'a> {
+    target: Origin<'a>,
+    scratch: Scratch<'a>,
+    dirty: Option<Range<usize>>,
+    /// Only needed once a savepoint has been taken
+    #[cfg(feature = "alloc")]
+    journal: Option<Journal>,
+}
+
+/// The string that a copy is taken from
+enum Origin<'a> {
+    /// A string that the changes are written back into
+    Exclusive(&'a mut str),
+    /// A string that may be shared, so the changes become a new string instead
+    #[cfg(feature = "alloc")]
+    Shared(&'a str),
+    /// A copy of a string that can't be borrowed mutably, which is written back afterwards
+    #[cfg(feature = "alloc")]
+    Owned(String),
+}
+
+impl<'a> Origin<'a> {
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::Exclusive(s) => s.as_bytes(),
+            #[cfg(feature = "alloc")]
+            Self::Shared(s) => s.as_bytes(),
+            #[cfg(feature = "alloc")]
+            Self::Owned(s) => s.as_bytes(),
+        }
+    }
+
+    /// The string to write changes back into, or `None` if it may be shared.
+    fn as_mut(&mut self) -> Option<&mut str> {
+        match self {
+            Self::Exclusive(s) => Some(s),
+            #[cfg(feature = "alloc")]
+            Self::Shared(_) => None,
+            #[cfg(feature = "alloc")]
+            Self::Owned(s) => Some(s.as_mut_str()),
+        }
+    }
+}
+
+/// Where the copy of the original string is kept
+enum Scratch<'a> {
+    #[cfg(feature = "alloc")]
+    Heap(Option<Vec<u8>>),
+    Buffer { buf: &'a mut [u8], copied: bool },
+}
+
+impl<'a> Scratch<'a> {
+    fn get(&self) -> Option<&[u8]> {
+        match self {
+            #[cfg(feature = "alloc")]
+            Self::Heap(edited) => edited.as_deref(),
+            Self::Buffer { buf, copied } => copied.then_some(&**buf),
+        }
+    }
+
+    fn get_or_copy(&mut self, original: &[u8]) -> &mut [u8] {
+        match self {
+            #[cfg(feature = "alloc")]
+            Self::Heap(edited) => edited.get_or_insert_with(|| original.to_vec()),
+            Self::Buffer { buf, copied } => {
+                if !*copied {
+                    buf.copy_from_slice(original);
+                    *copied = true;
+                }
+                buf
+            }
+        }
+    }
+
+    /// Forget the copy so that the original is visible again.
+    fn reset(&mut self) {
+        match self {
+            #[cfg(feature = "alloc")]
+            Self::Heap(edited) => *edited = None,
+            Self::Buffer { copied, .. } => *copied = false,
+        }
+    }
+
+    /// Build the error for a copy that was not valid UTF-8, taking its contents if possible.
+    fn reject(&mut self, error: Utf8Error) -> Error {
+        let details = match self {
+            #[cfg(feature = "alloc")]
+            Self::Heap(edited) => InvalidUtf8Error::new(edited.take().unwrap_or_default(), error),
+            Self::Buffer { buf, .. } => InvalidUtf8Error::from_slice(buf, error),
+        };
+        Error::InvalidUtf8(details)
+    }
+}
+
+impl<'a> CopyOnWrite<'a> {
+    fn new(target: Origin<'a>, scratch: Scratch<'a>) -> Self {
+        Self {
+            target,
+            scratch,
+            dirty: None,
+            #[cfg(feature = "alloc")]
+            journal: None,
+        }
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self.scratch.get().unwrap_or(self.target.as_bytes())
+    }
+
+    fn write(&mut self, range: Range<usize>) -> &mut [u8] {
+        mark_dirty(&mut self.dirty, range.clone());
+        let buf = self.scratch.get_or_copy(self.target.as_bytes());
+        #[cfg(feature = "alloc")]
+        if let Some(journal) = &mut self.journal {
+            journal.record(buf, range.clone());
+        }
+        &mut buf[range]
+    }
+
+    fn write_all(&mut self) -> &mut [u8] {
+        mark_dirty(&mut self.dirty, 0..self.target.as_bytes().len());
+        let buf = self.scratch.get_or_copy(self.target.as_bytes());
+        #[cfg(feature = "alloc")]
+        if let Some(journal) = &mut self.journal {
+            journal.record_all(buf);
+        }
+        buf
+    }
+
+    #[cfg(feature = "alloc")]
+    fn savepoint(&mut self) -> Savepoint {
+        let copied = self.scratch.get().is_some();
+        let (mark, generation) = self.journal.get_or_insert_with(Journal::default).mark();
+        Savepoint { mark, generation, copied }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn rollback_to(&mut self, savepoint: Savepoint) {
+        let journal = self.journal.as_mut().expect("savepoint is no longer valid");
+        if savepoint.copied {
+            let buf = self.scratch.get_or_copy(self.target.as_bytes());
+            journal.rollback_to(buf, savepoint.mark, savepoint.generation);
+        } else {
+            // Nothing had been copied yet, so the savepoint is the original
+            journal.release_after(savepoint.generation);
+            journal.forget_entries();
+            self.scratch.reset();
+            self.dirty = None;
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn changes(&self) -> Vec<Range<usize>> {
+        match (self.scratch.get(), &self.dirty) {
+            (Some(edited), Some(dirty)) => {
+                validate::changed_runs(self.target.as_bytes(), edited, dirty.clone()).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Check whether the edited copy is valid UTF-8.
+    fn check(&self) -> Result<(), Utf8Error> {
+        match (self.scratch.get(), &self.dirty) {
+            (Some(edited), Some(dirty)) => validate::validate_changed(
+                edited,
+                validate::changed_runs(self.target.as_bytes(), edited, dirty.clone()),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    fn discard(&mut self) {
+        self.scratch.reset();
+        self.dirty = None;
+        // Keep the journal so that the generations of earlier savepoints are not reused
+        #[cfg(feature = "alloc")]
+        if let Some(journal) = &mut self.journal {
+            journal.clear();
+        }
+    }
+
+    fn commit(&mut self) -> Result<(), Error> {
+        let target = self.target.as_mut().expect("views of shared strings are finished with into_edited");
+        let res = match (self.scratch.get(), self.dirty.take()) {
+            (Some(edited), Some(dirty)) => commit_copy(target, edited, dirty),
+            _ => Ok(()),
+        };
+        let res = res.map_err(|e| self.scratch.reject(e));
+        self.discard();
+        res
+    }
+
+    /// Take the edited copy if it is valid UTF-8, or `None` if no bytes were changed.
+    #[cfg(feature = "alloc")]
+    fn take_edited(&mut self) -> Result<Option<String>, Error> {
+        if let Err(e) = self.check() {
+            let err = self.scratch.reject(e);
+            self.discard();
+            return Err(err);
+        }
+        // Bytes may have been written with the values they already had
+        let modified = match (self.scratch.get(), &self.dirty) {
+            (Some(edited), Some(dirty)) => {
+                validate::changed_runs(self.target.as_bytes(), edited, dirty.clone()).next().is_some()
+            }
+            _ => false,
+        };
+        let edited = match &mut self.scratch {
+            Scratch::Heap(edited) => edited.take(),
+            Scratch::Buffer { buf, copied } => copied.then(|| buf.to_vec()),
+        };
+        let edited = edited.filter(|_| modified);
+        self.discard();
+        // SAFETY: We just proved that the new content is valid UTF-8
+        Ok(edited.map(|v| unsafe { String::from_utf8_unchecked(v) }))
+    }
+}
+
+impl<'a> MutableStringBytes<'a> {
+    /// Create a view that copies `target` to the heap when first mutated.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn new(target: &'a mut str) -> Self {
+        Self {
+            storage: Storage::Copy(CopyOnWrite::new(Origin::Exclusive(target), Scratch::Heap(None))),
+        }
+    }
+
+    /// Create a view of a string that may be shared, which copies it to the heap when first
+    /// mutated. The result must be collected with [`into_edited`](Self::into_edited).
+    #[cfg(feature = "alloc")]
+    pub(crate) fn shared(target: &'a str) -> Self {
+        Self {
+            storage: Storage::Copy(CopyOnWrite::new(Origin::Shared(target), Scratch::Heap(None))),
+        }
+    }
+
+    /// Create a view of a string that may be shared, which copies it into `buf` when first
+    /// mutated. The two must be the same length.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn shared_with_buffer(target: &'a str, buf: &'a mut [u8]) -> Self {
+        debug_assert_eq!(target.len(), buf.len());
+        let scratch = Scratch::Buffer { buf, copied: false };
+        Self {
+            storage: Storage::Copy(CopyOnWrite::new(Origin::Shared(target), scratch)),
+        }
+    }
+
+    /// Create a view of an owned copy of a string, which can be taken back with
+    /// [`take_owned`](Self::take_owned) once the changes are committed.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn owned(target: String) -> MutableStringBytes<'static> {
+        MutableStringBytes {
+            storage: Storage::Copy(CopyOnWrite::new(Origin::Owned(target), Scratch::Heap(None))),
+        }
+    }
+
+    /// Create a view that copies `target` into `buf` when first mutated. The two must be the
+    /// same length.
+    pub(crate) fn with_buffer(target: &'a mut str, buf: &'a mut [u8]) -> Self {
+        debug_assert_eq!(target.len(), buf.len());
+        Self {
+            storage: Storage::Copy(CopyOnWrite::new(Origin::Exclusive(target), Scratch::Buffer { buf, copied: false })),
+        }
+    }
+
+    /// Create a view that edits `target` directly. `target` must contain valid UTF-8.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn in_place(target: &'a mut [u8]) -> Self {
+        Self {
+            storage: Storage::InPlace(InPlace::new(target)),
+        }
+    }
+
+    /// Overwrite the byte at `index`.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set(&mut self, index: usize, byte: u8) {
+        self.range_mut(index..index + 1)[0] = byte;
+    }
+
+    /// Overwrite the bytes starting at `offset` with the contents of `bytes`.
+    ///
+    /// Panics if the write would extend past the end of the buffer.
+    pub fn write_at(&mut self, offset: usize, bytes: &[u8]) {
+        self.range_mut(offset..offset + bytes.len()).copy_from_slice(bytes);
+    }
+
+    /// Get mutable access to a subrange of the buffer.
+    ///
+    /// Only this range is considered modified, which is cheaper to commit than mutating
+    /// through `DerefMut`. Panics if the range is out of bounds.
+    pub fn range_mut<R: RangeBounds<usize>>(&mut self, range: R) -> &mut [u8] {
+        let range = resolve_range(range, self.len());
+        match &mut self.storage {
+            Storage::Copy(copy) => copy.write(range),
+            #[cfg(feature = "alloc")]
+            Storage::InPlace(in_place) => in_place.write(range),
+        }
+    }
+
+    /// Overwrite every byte that is part of an invalid UTF-8 sequence with `substitute`,
+    /// returning the number of bytes replaced.
+    ///
+    /// Panics if `substitute` is not an ASCII byte.
+    pub fn replace_invalid(&mut self, substitute: u8) -> usize {
+        assert!(substitute.is_ascii(), "substitute byte {:#04x} is not ASCII", substitute);
+        let mut replaced = 0;
+        let mut pos = 0;
+        while let Some(chunk) = self[pos..].utf8_chunks().next() {
+            let (valid, invalid) = (chunk.valid().len(), chunk.invalid().len());
+            if invalid == 0 {
+                break;
+            }
+            let start = pos + valid;
+            self.range_mut(start..start + invalid).fill(substitute);
+            replaced += invalid;
+            pos = start + invalid;
+        }
+        replaced
+    }
+
+    /// Check whether the buffer currently contains valid UTF-8.
+    ///
+    /// Like the final commit, this only needs to examine the regions that were modified.
+    pub fn is_valid(&self) -> bool {
+        self.check().is_ok()
+    }
+
+    /// View the buffer as a string, if it currently contains valid UTF-8.
+    ///
+    /// Like [`is_valid`](Self::is_valid), this only needs to examine the regions that were modified.
+    pub fn to_str(&self) -> Result<&str, Utf8Error> {
+        self.check_utf8()?;
+        // SAFETY: We just proved that the content is valid UTF-8
+        Ok(unsafe { core::str::from_utf8_unchecked(self) })
+    }
+
+    fn check_utf8(&self) -> Result<(), Utf8Error> {
+        match &self.storage {
+            Storage::Copy(copy) => copy.check(),
+            #[cfg(feature = "alloc")]
+            Storage::InPlace(in_place) => in_place.check(),
+        }
+    }
+
+    /// Check whether the buffer currently contains valid UTF-8, describing the problem if not.
+    pub(crate) fn check(&self) -> Result<(), InvalidUtf8Error> {
+        self.check_utf8().map_err(|e| InvalidUtf8Error::from_slice(self, e))
+    }
+
+    /// Record the current contents so that later changes can be undone with
+    /// [`rollback_to`](Self::rollback_to).
+    ///
+    /// Changes made after a savepoint is taken are journaled in the same way as an in-place
+    /// edit, so the extra memory used is proportional to the number of bytes written.
+    #[cfg(feature = "alloc")]
+    pub fn savepoint(&mut self) -> Savepoint {
+        match &mut self.storage {
+            Storage::Copy(copy) => copy.savepoint(),
+            Storage::InPlace(in_place) => {
+                let (mark, generation) = in_place.mark();
+                Savepoint {
+                    mark,
+                    generation,
+                    copied: true,
+                }
+            }
+        }
+    }
+
+    /// Undo every change made since `savepoint` was taken, keeping earlier changes.
+    ///
+    /// Savepoints taken after this one can no longer be used. Panics if the savepoint has
+    /// already been invalidated by rolling back to an earlier one, or by discarding all changes.
+    #[cfg(feature = "alloc")]
+    pub fn rollback_to(&mut self, savepoint: Savepoint) {
+        match &mut self.storage {
+            Storage::Copy(copy) => copy.rollback_to(savepoint),
+            Storage::InPlace(in_place) => in_place.rollback_to(savepoint.mark, savepoint.generation),
+        }
+    }
+
+    /// The byte ranges that have been modified so far.
+    ///
+    /// When editing a copy, this compares the tracked regions against the original, so bytes
+    /// that were overwritten with the same value are not included. When editing in place
+    /// the original is no longer available, so every tracked write is included.
+    #[cfg(feature = "alloc")]
+    pub fn changes(&self) -> ChangeSet {
+        let ranges = match &self.storage {
+            Storage::Copy(copy) => copy.changes(),
+            Storage::InPlace(in_place) => in_place.changed_ranges(),
+        };
+        ChangeSet::new(ranges)
+    }
+
+    /// Throw away every modification made so far, restoring the original contents.
+    pub fn discard(&mut self) {
+        match &mut self.storage {
+            Storage::Copy(copy) => copy.discard(),
+            #[cfg(feature = "alloc")]
+            Storage::InPlace(in_place) => in_place.rollback(),
+        }
+    }
+
+    /// Write the changes into the target string if they are valid UTF-8, or discard them if not.
+    ///
+    /// Either way the view can continue to be used afterwards, starting from the target's
+    /// current contents.
+    pub(crate) fn commit(&mut self) -> Result<(), Error> {
+        match &mut self.storage {
+            Storage::Copy(copy) => copy.commit(),
+            #[cfg(feature = "alloc")]
+            Storage::InPlace(in_place) => in_place.commit(),
+        }
+    }
+
+    /// The contents before any changes were made, if they are still available.
+    ///
+    /// They are not available when editing in place.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn original(&self) -> Option<&[u8]> {
+        match &self.storage {
+            Storage::Copy(copy) => Some(copy.target.as_bytes()),
+            #[cfg(feature = "alloc")]
+            Storage::InPlace(_) => None,
+        }
+    }
+
+    /// Take the string from a view created with [`owned`](Self::owned), leaving it empty.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn take_owned(&mut self) -> String {
+        self.discard();
+        match &mut self.storage {
+            Storage::Copy(CopyOnWrite { target: Origin::Owned(s), .. }) => core::mem::take(s),
+            _ => unreachable!("view does not own its string"),
+        }
+    }
+
+    /// Finish editing a shared string, returning the edited copy if any bytes were changed
+    /// and it is valid UTF-8.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn into_edited(mut self) -> Result<Option<String>, Error> {
+        match &mut self.storage {
+            Storage::Copy(copy) => copy.take_edited(),
+            Storage::InPlace(_) => unreachable!("shared strings are never edited in place"),
+        }
+    }
+}
+
+/// Validate an edited copy of `target`, given that only bytes within `dirty` may have
+/// changed, and copy that region back if it is valid.
+fn commit_copy(target: &mut str, edited: &[u8], dirty: Range<usize>) -> Result<(), Utf8Error> {
+    validate::validate_changed(edited, validate::changed_runs(target.as_bytes(), edited, dirty.clone()))?;
+    // Bytes may have been written with the values they already had, so narrow the copy to
+    // the span that really changed, if any
+    let mut runs = validate::changed_runs(target.as_bytes(), edited, dirty);
+    let span = match runs.next() {
+        Some(first) => first.start..runs.last().map_or(first.end, |last| last.end),
+        None => return Ok(()),
+    };
+    // SAFETY: We just proved that the new content is valid UTF-8
+    unsafe { target.as_bytes_mut()[span.clone()].copy_from_slice(&edited[span]) };
+    Ok(())
+}
+
+fn mark_dirty(dirty: &mut Option<Range<usize>>, range: Range<usize>) {
+    if range.is_empty() {
+        return;
+    }
+    *dirty = Some(match dirty.take() {
+        Some(d) => d.start.min(range.start)..d.end.max(range.end),
+        None => range,
+    });
+}
+
+impl<'a> Deref for MutableStringBytes<'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        match &self.storage {
+            Storage::Copy(copy) => copy.bytes(),
+            #[cfg(feature = "alloc")]
+            Storage::InPlace(in_place) => in_place.bytes(),
+        }
+    }
+}
+
+impl<'a> DerefMut for MutableStringBytes<'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match &mut self.storage {
+            Storage::Copy(copy) => copy.write_all(),
+            #[cfg(feature = "alloc")]
+            Storage::InPlace(in_place) => in_place.write_all(),
+        }
+    }
+}
+
+/// Convert any range expression into a concrete range, panicking if it does not fit in `len`.
+pub(crate) fn resolve_range<R: RangeBounds<usize>>(range: R, len: usize) -> Range<usize> {
+    let start = match range.start_bound() {
+        Bound::Included(&s) => s,
+        Bound::Excluded(&s) => s + 1,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&e) => e + 1,
+        Bound::Excluded(&e) => e,
+        Bound::Unbounded => len,
+    };
+    assert!(start <= end, "range start {} is greater than end {}", start, end);
+    assert!(end <= len, "range end {} is out of bounds for length {}", end, len);
+    start..end
+}
@@ -0,0 +1,103 @@
+use alloc::vec::{self, Vec};
+use core::ops::Range;
+
+/// The byte ranges of a string that were modified by an edit
+///
+/// The ranges are sorted and do not overlap. See [`MutableStringBytes::changes`](crate::MutableStringBytes::changes)
+/// for how they are found.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangeSet {
+    ranges: Vec<Range<usize>>,
+}
+
+impl ChangeSet {
+    pub(crate) fn new(ranges: Vec<Range<usize>>) -> Self {
+        debug_assert!(ranges.windows(2).all(|w| w[0].end <= w[1].start));
+        Self { ranges }
+    }
+
+    /// Whether nothing was modified.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// The modified ranges, in ascending order.
+    pub fn ranges(&self) -> &[Range<usize>] {
+        &self.ranges
+    }
+
+    /// The smallest single range covering every modification, or `None` if nothing was modified.
+    pub fn span(&self) -> Option<Range<usize>> {
+        let first = self.ranges.first()?;
+        let last = self.ranges.last()?;
+        Some(first.start..last.end)
+    }
+
+    /// Whether any modified range overlaps `range`.
+    pub fn intersects(&self, range: Range<usize>) -> bool {
+        self.ranges.iter().any(|r| r.start < range.end && range.start < r.end)
+    }
+
+    /// Take ownership of the modified ranges.
+    pub fn into_ranges(self) -> Vec<Range<usize>> {
+        self.ranges
+    }
+}
+
+impl IntoIterator for ChangeSet {
+    type Item = Range<usize>;
+    type IntoIter = vec::IntoIter<Range<usize>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.ranges.into_iter()
+    }
+}
+
+impl<'c> IntoIterator for &'c ChangeSet {
+    type Item = &'c Range<usize>;
+    type IntoIter = core::slice::Iter<'c, Range<usize>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.ranges.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::WithCheckedBytes;
+
+    #[test]
+    fn reports_changed_runs() {
+        let mut my_string = "Hello world".to_owned();
+        let (_, changes) = my_string.with_checked_bytes_tracked_mut(|s| {
+            s.write_at(0, b"J");
+            s.write_at(4, b"o w");
+            s[10] = b'D';
+        }).unwrap();
+        assert_eq!(changes.ranges(), [0..1, 10..11]);
+        assert_eq!(changes.span(), Some(0..11));
+        assert!(changes.intersects(5..11));
+        assert!(!changes.intersects(1..10));
+        assert_eq!(my_string, "Jello worlD");
+    }
+
+    #[test]
+    fn unchanged() {
+        let mut my_string = "Hello".to_owned();
+        let (_, changes) = my_string.with_checked_bytes_tracked_mut(|s| {
+            s.set(1, b'e');
+        }).unwrap();
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn in_place_changes() {
+        let mut my_string = "Hello".to_owned();
+        my_string.with_checked_bytes_in_place_mut(|s| {
+            s.set(1, b'a');
+            s.write_at(3, b"p!");
+            assert_eq!(s.changes().into_ranges(), [1..2, 3..5]);
+        }).unwrap();
+        assert_eq!(my_string, "Halp!");
+    }
+}
@@ -0,0 +1,137 @@
+//! Character-aware queries on the current contents of a [`MutableStringBytes`].
+//!
+//! The buffer may not be valid UTF-8 in the middle of an edit, so these work from the bytes
+//! alone. Any byte that is not a UTF-8 continuation byte is treated as the start of a
+//! character, which agrees with `str` whenever the contents are valid.
+
+use crate::validate::is_continuation;
+use crate::{Error, MutableStringBytes};
+
+/// The length of the character that starts with `lead`, or `None` if it can't start one.
+fn char_len(lead: u8) -> Option<usize> {
+    match lead {
+        0x00..=0x7f => Some(1),
+        0xc2..=0xdf => Some(2),
+        0xe0..=0xef => Some(3),
+        0xf0..=0xf4 => Some(4),
+        _ => None,
+    }
+}
+
+impl<'a> MutableStringBytes<'a> {
+    /// Whether `index` is the start of a character or the end of the buffer.
+    ///
+    /// Returns `false` if `index` is past the end.
+    pub fn is_char_boundary(&self, index: usize) -> bool {
+        match self.get(index) {
+            Some(&byte) => !is_continuation(byte),
+            None => index == self.len(),
+        }
+    }
+
+    /// Decode the character that starts at `index`.
+    ///
+    /// Returns `None` if `index` is out of bounds or the bytes there are not a valid character.
+    pub fn char_at(&self, index: usize) -> Option<char> {
+        let len = char_len(*self.get(index)?)?;
+        let bytes = self.get(index..index + len)?;
+        core::str::from_utf8(bytes).ok()?.chars().next()
+    }
+
+    /// Find the character that byte `index` is part of, returning its starting offset and value.
+    ///
+    /// Returns `None` if `index` is out of bounds or is not part of a valid character.
+    pub fn char_containing(&self, index: usize) -> Option<(usize, char)> {
+        let mut start = index;
+        while start > 0 && index - start < 3 && is_continuation(*self.get(start)?) {
+            start -= 1;
+        }
+        let c = self.char_at(start)?;
+        (start + c.len_utf8() > index).then_some((start, c))
+    }
+
+    /// Convert a byte offset into the index of the character that starts there.
+    ///
+    /// The end of the buffer converts to the number of characters. Returns `None` if `offset`
+    /// is not a character boundary.
+    pub fn byte_to_char_index(&self, offset: usize) -> Option<usize> {
+        if !self.is_char_boundary(offset) {
+            return None;
+        }
+        Some(self[..offset].iter().filter(|&&b| !is_continuation(b)).count())
+    }
+
+    /// Convert a character index into the byte offset where that character starts.
+    ///
+    /// The number of characters converts to the length of the buffer. Returns `None` if there
+    /// are fewer characters than that.
+    pub fn char_to_byte_index(&self, char_index: usize) -> Option<usize> {
+        let mut starts = self
+            .iter()
+            .enumerate()
+            .filter(|(_, &b)| !is_continuation(b))
+            .map(|(offset, _)| offset)
+            .chain(core::iter::once(self.len()));
+        starts.nth(char_index)
+    }
+
+    /// Overwrite the bytes starting at `offset` with `s`, provided this doesn't split a character
+    /// at either end.
+    ///
+    /// If it would, nothing is written and [`Error::NotCharBoundary`] reports the offending
+    /// offset. Panics if the write would extend past the end of the buffer.
+    pub fn overwrite_str_at(&mut self, offset: usize, s: &str) -> Result<(), Error> {
+        let end = offset + s.len();
+        assert!(end <= self.len(), "write of {} bytes at {} is past the end of the buffer", s.len(), offset);
+        for index in [offset, end] {
+            if !self.is_char_boundary(index) {
+                return Err(Error::NotCharBoundary { index });
+            }
+        }
+        self.write_at(offset, s.as_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, WithCheckedBytes};
+
+    #[test]
+    fn boundaries_and_indices() {
+        let mut my_string = String::from("aé€𝄞!");
+        my_string.with_checked_bytes_stack_mut::<16, _, _>(|s| {
+            assert!(s.is_char_boundary(1));
+            assert!(!s.is_char_boundary(2));
+            assert!(s.is_char_boundary(11));
+            assert!(!s.is_char_boundary(12));
+            assert_eq!(s.char_at(3), Some('€'));
+            assert_eq!(s.char_at(4), None);
+            assert_eq!(s.char_containing(9), Some((6, '𝄞')));
+            assert_eq!(s.byte_to_char_index(6), Some(3));
+            assert_eq!(s.byte_to_char_index(7), None);
+            assert_eq!(s.char_to_byte_index(4), Some(10));
+            assert_eq!(s.char_to_byte_index(5), Some(11));
+            assert_eq!(s.char_to_byte_index(6), None);
+
+            // Queries reflect edits made so far, even if they are not yet valid
+            s[3] = b'x';
+            assert_eq!(s.char_at(3), Some('x'));
+            assert!(s.is_char_boundary(3));
+            assert_eq!(s.char_containing(4), None);
+            s.discard();
+        }).unwrap();
+    }
+
+    #[test]
+    fn checked_overwrite() {
+        let mut my_string = String::from("año 2024");
+        my_string.with_checked_bytes_stack_mut::<16, _, _>(|s| {
+            assert!(matches!(s.overwrite_str_at(2, "n"), Err(Error::NotCharBoundary { index: 2 })));
+            assert!(matches!(s.overwrite_str_at(0, "ab"), Err(Error::NotCharBoundary { index: 2 })));
+            s.overwrite_str_at(1, "ñ").unwrap();
+            s.overwrite_str_at(6, "025").unwrap();
+        }).unwrap();
+        assert_eq!(my_string, "año 2025");
+    }
+}
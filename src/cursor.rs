@@ -0,0 +1,169 @@
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::{MutableStringBytes, MutableStringVec};
+
+/// A cursor over a [`MutableStringBytes`] implementing the `std::io` traits
+///
+/// This allows existing serializers and formatters to read and overwrite regions of the
+/// string. Only the bytes written are considered modified, and the result is still validated
+/// when the edit commits. Create one with [`MutableStringBytes::cursor`].
+///
+/// The length of the string is fixed, so writing at or past the end returns an error of kind
+/// [`io::ErrorKind::WriteZero`] instead of growing it. A write that only partly fits is
+/// truncated, in the same way as `io::Cursor<&mut [u8]>`.
+pub struct StringBytesCursor<'v, 'a> {
+    view: &'v mut MutableStringBytes<'a>,
+    pos: u64,
+}
+
+impl<'a> MutableStringBytes<'a> {
+    /// Create a cursor for reading and writing the buffer through `std::io`, starting at the
+    /// beginning.
+    pub fn cursor(&mut self) -> StringBytesCursor<'_, 'a> {
+        StringBytesCursor { view: self, pos: 0 }
+    }
+}
+
+impl<'v, 'a> StringBytesCursor<'v, 'a> {
+    /// The current position in bytes.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Move to a new position in bytes. This may be past the end of the buffer.
+    pub fn set_position(&mut self, pos: u64) {
+        self.pos = pos;
+    }
+
+    /// Access the underlying view.
+    pub fn get_mut(&mut self) -> &mut MutableStringBytes<'a> {
+        self.view
+    }
+
+    /// Give back the underlying view.
+    pub fn into_inner(self) -> &'v mut MutableStringBytes<'a> {
+        self.view
+    }
+
+    /// The current position as an index, clamped to the end of the buffer.
+    fn index(&self) -> usize {
+        usize::try_from(self.pos).map_or(self.view.len(), |pos| pos.min(self.view.len()))
+    }
+}
+
+impl<'v, 'a> Read for StringBytesCursor<'v, 'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let start = self.index();
+        let len = buf.len().min(self.view.len() - start);
+        buf[..len].copy_from_slice(&self.view[start..start + len]);
+        self.pos = (start + len) as u64;
+        Ok(len)
+    }
+}
+
+impl<'v, 'a> Write for StringBytesCursor<'v, 'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let start = self.index();
+        let len = buf.len().min(self.view.len() - start);
+        if len == 0 {
+            return Err(io::Error::new(io::ErrorKind::WriteZero, "write past the end of the string"));
+        }
+        self.view.write_at(start, &buf[..len]);
+        self.pos = (start + len) as u64;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'v, 'a> Seek for StringBytesCursor<'v, 'a> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(n) => {
+                self.pos = n;
+                return Ok(n);
+            }
+            SeekFrom::End(n) => (self.view.len() as u64, n),
+            SeekFrom::Current(n) => (self.pos, n),
+        };
+        match base.checked_add_signed(offset) {
+            Some(n) => {
+                self.pos = n;
+                Ok(n)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.pos)
+    }
+}
+
+/// Appends to the end of the vector, in the same way as `Vec<u8>`.
+impl<'a> Write for MutableStringVec<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{WithCheckedBytes, WithCheckedVec};
+
+    #[test]
+    fn overwrite_region() {
+        let mut my_string = String::from("id=0000 name=?");
+        my_string.with_checked_bytes_mut(|s| {
+            let mut cursor = s.cursor();
+            cursor.seek(SeekFrom::Start(3)).unwrap();
+            write!(cursor, "{:04}", 42).unwrap();
+            cursor.seek(SeekFrom::End(-1)).unwrap();
+            let err = write!(cursor, "Bob").unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+            assert_eq!(s.changes().into_ranges(), [5..7, 13..14]);
+        }).unwrap();
+        assert_eq!(my_string, "id=0042 name=B");
+    }
+
+    #[test]
+    fn read_and_seek() {
+        let mut my_string = String::from("Hello wörld");
+        my_string.with_checked_bytes_mut(|s| {
+            let mut cursor = s.cursor();
+            let mut word = [0; 5];
+            cursor.read_exact(&mut word).unwrap();
+            assert_eq!(&word, b"Hello");
+            assert_eq!(cursor.seek(SeekFrom::Current(3)).unwrap(), 8);
+            assert!(cursor.seek(SeekFrom::Current(-9)).is_err());
+            let mut rest = Vec::new();
+            cursor.read_to_end(&mut rest).unwrap();
+            assert_eq!(rest, b"\xb6rld");
+            // Splitting a character is only caught when the edit commits
+            cursor.seek(SeekFrom::Start(7)).unwrap();
+            cursor.write_all(b"o").unwrap();
+        }).unwrap_err();
+        assert_eq!(my_string, "Hello wörld");
+    }
+
+    #[test]
+    fn vec_grows() {
+        let mut my_string = String::from("total: ");
+        my_string.with_checked_vec_mut(|s| write!(s, "{}€", 12)).unwrap().unwrap();
+        assert_eq!(my_string, "total: 12€");
+    }
+}
@@ -0,0 +1,243 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::str::Utf8Error;
+
+#[cfg(feature = "alloc")]
+use crate::WriteProvenance;
+
+/// Errors that can occur while mutating strings
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The edited buffer did not contain valid UTF-8, so the original string was not modified
+    InvalidUtf8(InvalidUtf8Error),
+    /// The scratch buffer supplied for the edit was shorter than the string
+    BufferTooSmall { needed: usize, available: usize },
+    /// An ASCII-only edit was requested but the string contains a non-ASCII byte at `index`
+    NotAscii { index: usize },
+    /// The edited string is longer than the fixed capacity of the string type being edited
+    CapacityExceeded { needed: usize, capacity: usize },
+    /// The edited string was valid UTF-8 but a [`Validator`](crate::Validator) rejected it
+    Rejected(Rejection),
+    /// A record field needed a different number of bytes than it occupies in a fixed-length string
+    FieldLength { needed: usize, available: usize },
+    /// A string of `len` bytes is too short to contain field `field` of a record layout
+    RecordTooShort { field: usize, len: usize },
+    /// A string would have been written at `index`, splitting the character there
+    NotCharBoundary { index: usize },
+    /// The byte at `index` is in a protected range but was modified, so the original string was
+    /// not modified
+    Protected { index: usize },
+    /// A strict edit did not contain valid UTF-8, and `write` was the last write to touch the
+    /// first invalid sequence
+    #[cfg(feature = "alloc")]
+    InvalidWrite { error: InvalidUtf8Error, write: WriteProvenance },
+}
+
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::InvalidUtf8(e) => Some(&e.error),
+            #[cfg(feature = "alloc")]
+            Self::InvalidWrite { error, .. } => Some(&error.error),
+            Self::BufferTooSmall { .. }
+            | Self::NotAscii { .. }
+            | Self::CapacityExceeded { .. }
+            | Self::Rejected(_)
+            | Self::FieldLength { .. }
+            | Self::RecordTooShort { .. }
+            | Self::NotCharBoundary { .. }
+            | Self::Protected { .. } => None,
+        }
+    }
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidUtf8(e) => write!(
+                f,
+                "MutableStringBytes contains invalid UTF-8 after modifications (valid up to byte {})",
+                e.valid_up_to()
+            ),
+            Self::BufferTooSmall { needed, available } => write!(
+                f,
+                "scratch buffer of {} bytes is too small to edit a string of {} bytes",
+                available, needed
+            ),
+            Self::NotAscii { index } => write!(f, "string contains a non-ASCII byte at index {}", index),
+            Self::CapacityExceeded { needed, capacity } => write!(
+                f,
+                "edited string of {} bytes does not fit in a capacity of {} bytes",
+                needed, capacity
+            ),
+            Self::Rejected(r) => write!(f, "edited string was rejected at byte {}: {}", r.index, r.reason),
+            Self::FieldLength { needed, available } => write!(
+                f,
+                "field value of {} bytes cannot replace {} bytes in a fixed-length string",
+                needed, available
+            ),
+            Self::RecordTooShort { field, len } => {
+                write!(f, "record of {} bytes is too short to contain field {}", len, field)
+            }
+            Self::NotCharBoundary { index } => write!(f, "writing at byte {} would split a character", index),
+            Self::Protected { index } => write!(f, "byte {} is protected but was modified", index),
+            #[cfg(feature = "alloc")]
+            Self::InvalidWrite { error, write } => write!(
+                f,
+                "MutableStringBytes contains invalid UTF-8 at byte {}, last written by write #{} at {}",
+                error.valid_up_to(),
+                write.order,
+                write.location
+            ),
+        }
+    }
+}
+
+/// The reason a [`Validator`](crate::Validator) rejected a string
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rejection {
+    /// Byte offset of the first character that broke the rule
+    pub index: usize,
+    /// Description of the rule that was broken
+    pub reason: &'static str,
+}
+
+/// Errors that can occur during a fallible edit
+#[derive(Debug)]
+pub enum TryError<E> {
+    /// The closure returned an error, so the original string was not modified
+    Closure(E),
+    /// The closure succeeded but its changes could not be committed
+    Checked(Error),
+}
+
+impl<E> From<Error> for TryError<E> {
+    fn from(e: Error) -> Self {
+        Self::Checked(e)
+    }
+}
+
+impl<E: core::error::Error + 'static> core::error::Error for TryError<E> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Closure(e) => Some(e),
+            Self::Checked(e) => Some(e),
+        }
+    }
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for TryError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Closure(e) => write!(f, "edit was abandoned: {}", e),
+            Self::Checked(e) => write!(f, "edit could not be committed: {}", e),
+        }
+    }
+}
+
+/// Error from an edit of several strings at once, none of which were modified
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchError {
+    failures: Vec<(usize, InvalidUtf8Error)>,
+}
+
+#[cfg(feature = "alloc")]
+impl BatchError {
+    pub(crate) fn new(failures: Vec<(usize, InvalidUtf8Error)>) -> Self {
+        Self { failures }
+    }
+
+    /// The positions of the strings that were not valid UTF-8, in ascending order.
+    pub fn failed_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.failures.iter().map(|(index, _)| *index)
+    }
+
+    /// The position and details of each string that was not valid UTF-8.
+    pub fn failures(&self) -> &[(usize, InvalidUtf8Error)] {
+        &self.failures
+    }
+
+    /// Take ownership of the position and details of each string that was not valid UTF-8.
+    pub fn into_failures(self) -> Vec<(usize, InvalidUtf8Error)> {
+        self.failures
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl core::error::Error for BatchError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        self.failures.first().map(|(_, e)| &e.error as _)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl core::fmt::Display for BatchError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "edited strings contain invalid UTF-8 at positions")?;
+        for (i, index) in self.failed_indices().enumerate() {
+            let sep = if i == 0 { " " } else { ", " };
+            write!(f, "{}{}", sep, index)?;
+        }
+        Ok(())
+    }
+}
+
+/// Details of an edit that was rejected because it was not valid UTF-8
+///
+/// The rejected bytes are kept so that they can be inspected, logged or repaired. This
+/// requires the `alloc` feature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidUtf8Error {
+    #[cfg(feature = "alloc")]
+    bytes: Vec<u8>,
+    error: Utf8Error,
+}
+
+impl InvalidUtf8Error {
+    #[cfg(feature = "alloc")]
+    pub(crate) fn new(bytes: Vec<u8>, error: Utf8Error) -> Self {
+        Self { bytes, error }
+    }
+
+    /// Create an error from rejected bytes that are borrowed, copying them if possible.
+    pub(crate) fn from_slice(bytes: &[u8], error: Utf8Error) -> Self {
+        #[cfg(feature = "alloc")]
+        return Self::new(bytes.to_vec(), error);
+        #[cfg(not(feature = "alloc"))]
+        {
+            let _ = bytes;
+            Self { error }
+        }
+    }
+
+    /// The number of bytes at the start of the edited buffer that were valid UTF-8.
+    pub fn valid_up_to(&self) -> usize {
+        self.error.valid_up_to()
+    }
+
+    /// The length of the invalid sequence, or `None` if the buffer ended partway through a character.
+    ///
+    /// See [`Utf8Error::error_len`] for details.
+    pub fn error_len(&self) -> Option<usize> {
+        self.error.error_len()
+    }
+
+    /// The underlying UTF-8 validation error.
+    pub fn utf8_error(&self) -> Utf8Error {
+        self.error
+    }
+
+    /// The edited bytes that were rejected.
+    #[cfg(feature = "alloc")]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Take ownership of the edited bytes that were rejected.
+    #[cfg(feature = "alloc")]
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
@@ -0,0 +1,193 @@
+use core::fmt;
+use core::ops::{Range, RangeBounds};
+
+use crate::bytes::resolve_range;
+use crate::MutableStringBytes;
+
+/// What a [`FieldWriter`] does when formatted output doesn't fit in its field
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Write nothing from the piece of output that doesn't fit and return [`fmt::Error`]. The
+    /// rest of the field keeps its previous contents instead of being padded.
+    #[default]
+    Error,
+    /// Write as many whole characters as fit and silently drop the rest.
+    Truncate,
+}
+
+/// Formats text into a fixed-width byte range of a [`MutableStringBytes`]
+///
+/// Create one with [`MutableStringBytes::field`]. When the writer is finished or dropped, the
+/// part of the field that hasn't been written is filled with the padding byte, so whatever is
+/// written is left-aligned. Use format specifiers such as `{:>8}` for other alignments.
+///
+/// Output is only ever cut at a character boundary, so a multi-byte character is never split.
+/// Once output has overflowed, everything written afterwards is dropped. With
+/// [`Overflow::Error`] the field is then not padded, so a value that is rejected outright
+/// leaves the field as it was.
+///
+/// ```
+/// use core::fmt::Write;
+/// # use with_checked_bytes::WithCheckedBytes;
+/// let mut record = String::from("[     ][abc]");
+/// record.with_checked_bytes_stack_mut::<16, _, _>(|s| {
+///     write!(s.field(1..6), "{:.1}", 3.14159).unwrap();
+///     assert!(write!(s.field(8..11), "{}", 12345).is_err());
+/// }).unwrap();
+/// assert_eq!(record, "[3.1  ][abc]");
+/// ```
+pub struct FieldWriter<'v, 'a> {
+    view: &'v mut MutableStringBytes<'a>,
+    range: Range<usize>,
+    pos: usize,
+    pad: u8,
+    overflow: Overflow,
+    overflowed: bool,
+}
+
+impl<'a> MutableStringBytes<'a> {
+    /// Create a writer that formats into `range`, padding the rest of it with spaces when it
+    /// is finished.
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn field<R: RangeBounds<usize>>(&mut self, range: R) -> FieldWriter<'_, 'a> {
+        let range = resolve_range(range, self.len());
+        FieldWriter {
+            pos: range.start,
+            view: self,
+            range,
+            pad: b' ',
+            overflow: Overflow::default(),
+            overflowed: false,
+        }
+    }
+}
+
+impl<'v, 'a> FieldWriter<'v, 'a> {
+    /// Pad the field with `pad` instead of spaces.
+    ///
+    /// Panics if `pad` is not an ASCII byte.
+    pub fn with_padding(mut self, pad: u8) -> Self {
+        assert!(pad.is_ascii(), "padding byte {:#04x} is not ASCII", pad);
+        self.pad = pad;
+        self
+    }
+
+    /// Choose what happens when output doesn't fit.
+    pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// The number of bytes written into the field so far.
+    pub fn len(&self) -> usize {
+        self.pos - self.range.start
+    }
+
+    /// Whether nothing has been written into the field yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of bytes still available.
+    pub fn remaining(&self) -> usize {
+        self.range.end - self.pos
+    }
+
+    /// Whether any output has been dropped or rejected because it didn't fit.
+    pub fn overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    /// Pad the rest of the field and stop writing. This also happens when the writer is
+    /// dropped.
+    pub fn finish(self) {}
+}
+
+impl<'v, 'a> Drop for FieldWriter<'v, 'a> {
+    fn drop(&mut self) {
+        if self.overflowed && self.overflow == Overflow::Error {
+            return;
+        }
+        let (start, end) = (self.pos, self.range.end);
+        if start < end {
+            self.view.range_mut(start..end).fill(self.pad);
+        }
+    }
+}
+
+impl<'v, 'a> fmt::Write for FieldWriter<'v, 'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.overflowed {
+            return match self.overflow {
+                Overflow::Error => Err(fmt::Error),
+                Overflow::Truncate => Ok(()),
+            };
+        }
+        let mut len = s.len();
+        if len > self.remaining() {
+            self.overflowed = true;
+            match self.overflow {
+                Overflow::Error => return Err(fmt::Error),
+                Overflow::Truncate => {
+                    len = self.remaining();
+                    while !s.is_char_boundary(len) {
+                        len -= 1;
+                    }
+                }
+            }
+        }
+        self.view.write_at(self.pos, &s.as_bytes()[..len]);
+        self.pos += len;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::fmt::Write;
+
+    use super::*;
+    use crate::WithCheckedBytes;
+
+    #[test]
+    fn pad_and_truncate() {
+        let mut record = String::from("name:__________|");
+        record.with_checked_bytes_stack_mut::<16, _, _>(|s| {
+            let mut field = s.field(5..15).with_padding(b'.').with_overflow(Overflow::Truncate);
+            // Only 1 byte is left for the 2 byte "é", so the output stops before it
+            let other = "Chloé";
+            write!(field, "Zoë {}", other).unwrap();
+            assert!(field.overflowed());
+            assert_eq!(field.len(), 9);
+        }).unwrap();
+        assert_eq!(record, "name:Zoë Chlo.|");
+    }
+
+    #[test]
+    fn overflow_error() {
+        let mut record = String::from("abcdef");
+        record.with_checked_bytes_stack_mut::<16, _, _>(|s| {
+            let mut field = s.field(1..5);
+            write!(field, "{}", 12).unwrap();
+            assert!(write!(field, "{}", 345).is_err());
+            assert!(write!(field, "6").is_err());
+            assert_eq!(field.remaining(), 2);
+        }).unwrap();
+        // Only the output that fitted was written, and the rest wasn't padded
+        assert_eq!(record, "a12def");
+    }
+
+    #[test]
+    fn rejected_value_keeps_field() {
+        let mut record = String::from("[abcd][ef]");
+        record.with_checked_bytes_stack_mut::<16, _, _>(|s| {
+            assert!(write!(s.field(1..5), "{}", 123456).is_err());
+            let mut field = s.field(7..9).with_padding(b'_');
+            write!(field, "x").unwrap();
+            field.finish();
+            assert_eq!(&s[..], b"[abcd][x_]");
+        }).unwrap();
+        assert_eq!(record, "[abcd][x_]");
+    }
+}
@@ -0,0 +1,31 @@
+use ::arrayvec::ArrayString;
+
+impl_via_as_mut_str!([const CAP: usize] ArrayString<CAP>);
+#[cfg(feature = "alloc")]
+impl_vec_via_push_str!([const CAP: usize] ArrayString<CAP>, capacity = CAP);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WithCheckedBytes;
+
+    #[test]
+    fn edit_without_alloc() {
+        let mut my_string = ArrayString::<8>::from("Hello").unwrap();
+        my_string.with_checked_bytes_stack_mut::<8, _, _>(|s| s.set(0, b'J')).unwrap();
+        assert_eq!(&my_string, "Jello");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn grow_to_capacity() {
+        use crate::{Error, WithCheckedVec};
+
+        let mut my_string = ArrayString::<8>::from("Jello").unwrap();
+        let err = my_string.with_checked_vec_mut(|s| s.extend_from_slice(b" world")).unwrap_err();
+        assert!(matches!(err, Error::CapacityExceeded { needed: 11, capacity: 8 }));
+        assert_eq!(&my_string, "Jello");
+        my_string.with_checked_vec_mut(|s| s.extend_from_slice("!ö".as_bytes())).unwrap();
+        assert_eq!(&my_string, "Jello!ö");
+    }
+}
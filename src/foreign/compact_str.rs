@@ -0,0 +1,22 @@
+use ::compact_str::CompactString;
+
+impl_via_as_mut_str!([] CompactString);
+impl_vec_via_push_str!([] CompactString);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{WithCheckedBytes, WithCheckedVec};
+
+    #[test]
+    fn edit_and_grow() {
+        let mut my_string = CompactString::from("Hello");
+        my_string.with_checked_bytes_mut(|s| s.set(0, b'J')).unwrap();
+        my_string.with_checked_vec_mut(|s| s.extend_from_slice(" wörld".as_bytes())).unwrap();
+        assert_eq!(my_string, "Jello wörld");
+        my_string.with_checked_vec_mut(|s| {
+            s.splice(7..9, "o".bytes());
+        }).unwrap();
+        assert_eq!(my_string, "Jello world");
+    }
+}
@@ -0,0 +1,33 @@
+use ::heapless::String;
+
+impl_via_as_mut_str!([const N: usize] String<N>);
+#[cfg(feature = "alloc")]
+impl_vec_via_push_str!([const N: usize] String<N>, capacity = N);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, WithCheckedBytes};
+
+    #[test]
+    fn edit_without_alloc() {
+        let mut my_string = String::<8>::try_from("héllo").unwrap();
+        let err = my_string.with_ascii_bytes_mut(|_| ()).unwrap_err();
+        assert!(matches!(err, Error::NotAscii { index: 1 }));
+        my_string.with_checked_bytes_stack_mut::<8, _, _>(|s| s.set(2, 0xaa)).unwrap();
+        assert_eq!(my_string, "hêllo");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn change_first_char() {
+        use crate::WithCheckedVec;
+
+        let mut my_string = String::<8>::try_from("éa").unwrap();
+        my_string.with_checked_vec_mut(|s| {
+            s[1] = 0xaa;
+            s.push(b'!');
+        }).unwrap();
+        assert_eq!(my_string, "êa!");
+    }
+}
@@ -0,0 +1,85 @@
+//! Implementations for string types from other crates, each behind a feature of the same name
+
+/// Implement `WithCheckedBytes` for a type that can lend out its contents as a `&mut str`,
+/// so that changes are committed directly into its own storage.
+#[cfg(any(feature = "compact_str", feature = "arrayvec", feature = "heapless"))]
+macro_rules! impl_via_as_mut_str {
+    ([$($generics:tt)*] $ty:ty) => {
+        impl<$($generics)*> crate::sealed::Sealed for $ty {}
+
+        impl<$($generics)*> crate::WithCheckedBytes for $ty {
+            #[cfg(feature = "alloc")]
+            fn with_checked_bytes_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, crate::Error>
+            where
+                F: for<'b> FnOnce(&'b mut crate::MutableStringBytes) -> R,
+            {
+                self.as_mut_str().with_checked_bytes_mut(f)
+            }
+
+            #[cfg(feature = "alloc")]
+            fn with_checked_bytes_in_place_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, crate::Error>
+            where
+                F: for<'b> FnOnce(&'b mut crate::MutableStringBytes) -> R,
+            {
+                self.as_mut_str().with_checked_bytes_in_place_mut(f)
+            }
+
+            fn with_checked_bytes_in_mut<'a, R, F>(&'a mut self, scratch: &mut [u8], f: F) -> Result<R, crate::Error>
+            where
+                F: for<'b> FnOnce(&'b mut crate::MutableStringBytes) -> R,
+            {
+                self.as_mut_str().with_checked_bytes_in_mut(scratch, f)
+            }
+
+            #[cfg(feature = "alloc")]
+            fn checked_bytes_guard(&mut self) -> crate::CheckedBytesGuard<'_> {
+                self.as_mut_str().checked_bytes_guard()
+            }
+
+            fn with_ascii_bytes_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, crate::Error>
+            where
+                F: for<'b> FnOnce(&'b mut [crate::AsciiByte]) -> R,
+            {
+                self.as_mut_str().with_ascii_bytes_mut(f)
+            }
+        }
+    };
+}
+
+/// Implement `WithCheckedVec` for a type that can be truncated and appended to.
+///
+/// Only the part of the string from the first changed character onwards is rewritten. If
+/// `capacity` is given, edits that would not fit are rejected with
+/// [`Error::CapacityExceeded`](crate::Error::CapacityExceeded) before the string is touched.
+#[cfg(all(feature = "alloc", any(feature = "compact_str", feature = "arrayvec", feature = "heapless")))]
+macro_rules! impl_vec_via_push_str {
+    ([$($generics:tt)*] $ty:ty $(, capacity = $cap:expr)?) => {
+        impl<$($generics)*> crate::WithCheckedVec for $ty {
+            fn with_checked_vec_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, crate::Error>
+            where
+                F: for<'b> FnOnce(&'b mut crate::MutableStringVec) -> R,
+            {
+                let (res, edited) = crate::vec::edit(self, f)?;
+                if let Some((edited, start)) = edited {
+                    $(
+                        if edited.len() > $cap {
+                            return Err(crate::Error::CapacityExceeded { needed: edited.len(), capacity: $cap });
+                        }
+                    )?
+                    self.truncate(start);
+                    let _ = self.push_str(&edited[start..]);
+                }
+                Ok(res)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "arrayvec")]
+mod arrayvec;
+#[cfg(feature = "compact_str")]
+mod compact_str;
+#[cfg(feature = "heapless")]
+mod heapless;
+#[cfg(feature = "smol_str")]
+mod smol_str;
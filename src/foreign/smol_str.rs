@@ -0,0 +1,81 @@
+use ::smol_str::SmolStr;
+use alloc::string::ToString;
+
+use crate::pointers::{edit_shared, edit_shared_in};
+use crate::{AsciiByte, CheckedBytesGuard, Error, MutableStringBytes, WithCheckedBytes};
+
+/// A `SmolStr` is immutable, so an edit that changes it produces a new `SmolStr`. Nothing is
+/// copied unless the closure writes.
+///
+/// [`checked_bytes_guard`](WithCheckedBytes::checked_bytes_guard) and
+/// [`with_ascii_bytes_mut`](WithCheckedBytes::with_ascii_bytes_mut) always copy the string.
+impl crate::sealed::Sealed for SmolStr {}
+
+impl WithCheckedBytes for SmolStr {
+    fn with_checked_bytes_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+    {
+        let (res, edited) = edit_shared(self, f)?;
+        if let Some(edited) = edited {
+            *self = edited.into();
+        }
+        Ok(res)
+    }
+
+    fn with_checked_bytes_in_mut<'a, R, F>(&'a mut self, scratch: &mut [u8], f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+    {
+        let (res, edited) = edit_shared_in(self, scratch, f)?;
+        if let Some(edited) = edited {
+            *self = edited.into();
+        }
+        Ok(res)
+    }
+
+    fn checked_bytes_guard(&mut self) -> CheckedBytesGuard<'_> {
+        let copy = self.to_string();
+        CheckedBytesGuard::with_write_back(copy, move |edited| *self = edited.into())
+    }
+
+    fn with_ascii_bytes_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut [AsciiByte]) -> R,
+    {
+        let mut copy = self.to_string();
+        let res = copy.with_ascii_bytes_mut(f)?;
+        *self = copy.into();
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_and_guard() {
+        let mut my_string = SmolStr::new("Hello");
+        my_string.with_checked_bytes_mut(|s| s.set(0, b'J')).unwrap();
+        assert_eq!(my_string, "Jello");
+        let mut guard = my_string.checked_bytes_guard();
+        guard.set(1, b'a');
+        guard.commit().unwrap();
+        assert_eq!(my_string, "Jallo");
+    }
+
+    #[test]
+    fn unchanged_edit_keeps_shared() {
+        let mut my_string = SmolStr::new("a string too long to be stored inline");
+        let other = my_string.clone();
+        assert!(my_string.is_heap_allocated());
+        my_string.with_checked_bytes_mut(|s| s.set(0, b'a')).unwrap();
+        assert_eq!(my_string.as_ptr(), other.as_ptr());
+        my_string.with_checked_bytes_stack_mut::<64, _, _>(|s| s.set(0, b'a')).unwrap();
+        assert_eq!(my_string.as_ptr(), other.as_ptr());
+        my_string.with_checked_bytes_mut(|s| s.set(0, b'A')).unwrap();
+        assert_ne!(my_string.as_ptr(), other.as_ptr());
+        assert_eq!(other, "a string too long to be stored inline");
+    }
+}
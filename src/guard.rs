@@ -0,0 +1,258 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::ops::{Deref, DerefMut, Index, IndexMut, RangeBounds};
+use core::slice::SliceIndex;
+
+use crate::{Error, FieldWriter, MutableStringBytes, Record, RecordLayout, Savepoint};
+
+/// What a [`CheckedBytesGuard`] does with outstanding changes when it is dropped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DropPolicy {
+    /// Throw away any changes that were not explicitly committed
+    #[default]
+    Discard,
+    /// Commit the changes if they are valid UTF-8, otherwise throw them away
+    CommitIfValid,
+}
+
+/// An edit session over a string's bytes that lasts until the guard is dropped
+///
+/// The guard dereferences to a [`MutableStringBytes`] view for reading. It can be modified by
+/// indexing or with the methods here, which behave like the view's methods of the same name.
+/// Changes are only written to the string when [`commit`](Self::commit) is called, or when the
+/// guard is dropped with [`DropPolicy::CommitIfValid`].
+///
+/// The guard owns its view, so it never hands out a `&mut MutableStringBytes`. That could be
+/// swapped with the view of an in-place edit, and forgetting the guard would then leave invalid
+/// UTF-8 in the string:
+///
+/// ```compile_fail,E0596
+/// # use with_checked_bytes::WithCheckedBytes;
+/// let mut my_string = String::from("Hello");
+/// let other = Box::leak(Box::new(String::from("Jello")));
+/// my_string.with_checked_bytes_in_place_mut(|s| {
+///     let mut guard = other.checked_bytes_guard();
+///     s.set(0, 0xff);
+///     core::mem::swap(s, &mut *guard);
+///     core::mem::forget(guard);
+/// }).unwrap_err();
+/// ```
+pub struct CheckedBytesGuard<'a> {
+    view: MutableStringBytes<'a>,
+    policy: DropPolicy,
+    /// Stores the committed string for types that can't be edited through a `&mut str`
+    write_back: Option<Box<dyn FnOnce(String) + 'a>>,
+}
+
+impl<'a> CheckedBytesGuard<'a> {
+    pub(crate) fn new(view: MutableStringBytes<'a>) -> Self {
+        Self {
+            view,
+            policy: DropPolicy::default(),
+            write_back: None,
+        }
+    }
+
+    /// Create a guard over a copy of a string, passing the copy to `write_back` when the
+    /// changes are committed.
+    pub(crate) fn with_write_back<W>(copy: String, write_back: W) -> Self
+    where
+        W: FnOnce(String) + 'a,
+    {
+        Self {
+            view: MutableStringBytes::owned(copy),
+            policy: DropPolicy::default(),
+            write_back: Some(Box::new(write_back)),
+        }
+    }
+
+    /// Commit the changes, then pass the result to `write_back` if anything was modified.
+    ///
+    /// A copy's changes are compared against the original, so writing the bytes that were
+    /// already there doesn't count as a modification.
+    fn commit_and_write_back(&mut self) -> Result<(), Error> {
+        let modified = !self.view.changes().is_empty();
+        self.view.commit()?;
+        if let Some(write_back) = self.write_back.take().filter(|_| modified) {
+            write_back(self.view.take_owned());
+        }
+        Ok(())
+    }
+
+    /// Set what happens to outstanding changes when the guard is dropped.
+    pub fn with_drop_policy(mut self, policy: DropPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Write the changes into the string if they are valid UTF-8.
+    ///
+    /// If they are not, the string is left unmodified and an error is returned.
+    pub fn commit(mut self) -> Result<(), Error> {
+        self.policy = DropPolicy::Discard;
+        self.commit_and_write_back()
+    }
+
+    /// Throw away all changes, leaving the string unmodified.
+    pub fn rollback(mut self) {
+        self.policy = DropPolicy::Discard;
+        self.view.discard();
+    }
+
+    /// Overwrite the byte at `index`. See [`MutableStringBytes::set`].
+    pub fn set(&mut self, index: usize, byte: u8) {
+        self.view.set(index, byte);
+    }
+
+    /// Overwrite the bytes starting at `offset`. See [`MutableStringBytes::write_at`].
+    pub fn write_at(&mut self, offset: usize, bytes: &[u8]) {
+        self.view.write_at(offset, bytes);
+    }
+
+    /// Get mutable access to a subrange. See [`MutableStringBytes::range_mut`].
+    pub fn range_mut<R: RangeBounds<usize>>(&mut self, range: R) -> &mut [u8] {
+        self.view.range_mut(range)
+    }
+
+    /// Overwrite the bytes starting at `offset` with `s` without splitting a character. See
+    /// [`MutableStringBytes::overwrite_str_at`].
+    pub fn overwrite_str_at(&mut self, offset: usize, s: &str) -> Result<(), Error> {
+        self.view.overwrite_str_at(offset, s)
+    }
+
+    /// Replace every invalid sequence with `substitute`. See
+    /// [`MutableStringBytes::replace_invalid`].
+    pub fn replace_invalid(&mut self, substitute: u8) -> usize {
+        self.view.replace_invalid(substitute)
+    }
+
+    /// Remember the current contents. See [`MutableStringBytes::savepoint`].
+    pub fn savepoint(&mut self) -> Savepoint {
+        self.view.savepoint()
+    }
+
+    /// Undo every change made since `savepoint` was taken. See
+    /// [`MutableStringBytes::rollback_to`].
+    pub fn rollback_to(&mut self, savepoint: Savepoint) {
+        self.view.rollback_to(savepoint);
+    }
+
+    /// Throw away every modification made so far without ending the session. See
+    /// [`MutableStringBytes::discard`].
+    pub fn discard(&mut self) {
+        self.view.discard();
+    }
+
+    /// Create a writer that formats into `range`. See [`MutableStringBytes::field`].
+    pub fn field<R: RangeBounds<usize>>(&mut self, range: R) -> FieldWriter<'_, 'a> {
+        self.view.field(range)
+    }
+
+    /// Edit the buffer as a record. See [`MutableStringBytes::record`].
+    pub fn record<'l>(&mut self, layout: &'l RecordLayout) -> Record<'l, '_, MutableStringBytes<'a>> {
+        self.view.record(layout)
+    }
+}
+
+impl<'a> Deref for CheckedBytesGuard<'a> {
+    type Target = MutableStringBytes<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.view
+    }
+}
+
+impl<'a, I: SliceIndex<[u8]>> Index<I> for CheckedBytesGuard<'a> {
+    type Output = I::Output;
+
+    fn index(&self, index: I) -> &Self::Output {
+        &self.view[index]
+    }
+}
+
+/// Mutable indexing counts as a write in the same way as [`MutableStringBytes`]'s `DerefMut`.
+impl<'a, I: SliceIndex<[u8]>> IndexMut<I> for CheckedBytesGuard<'a> {
+    fn index_mut(&mut self, index: I) -> &mut Self::Output {
+        &mut self.view.deref_mut()[index]
+    }
+}
+
+impl<'a> Drop for CheckedBytesGuard<'a> {
+    fn drop(&mut self) {
+        match self.policy {
+            DropPolicy::Discard => self.view.discard(),
+            DropPolicy::CommitIfValid => {
+                let _ = self.commit_and_write_back();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::WithCheckedBytes;
+
+    use super::*;
+
+    #[test]
+    fn commit_and_rollback() {
+        let mut my_string = "Hello".to_owned();
+        let mut guard = my_string.checked_bytes_guard();
+        guard[0] = b'J';
+        guard.commit().unwrap();
+        let mut guard = my_string.checked_bytes_guard();
+        guard[0] = b'Y';
+        guard.rollback();
+        assert_eq!(my_string, "Jello");
+    }
+
+    #[test]
+    fn drop_policies() {
+        let mut my_string = "Hello".to_owned();
+        {
+            let mut guard = my_string.checked_bytes_guard();
+            guard.set(0, b'J');
+        }
+        assert_eq!(my_string, "Hello");
+        {
+            let mut guard = my_string.checked_bytes_guard().with_drop_policy(DropPolicy::CommitIfValid);
+            guard.set(0, b'J');
+        }
+        assert_eq!(my_string, "Jello");
+        {
+            let mut guard = my_string.checked_bytes_guard().with_drop_policy(DropPolicy::CommitIfValid);
+            guard.set(0, 0xff);
+        }
+        assert_eq!(my_string, "Jello");
+    }
+
+    #[test]
+    fn editing_methods() {
+        use core::fmt::Write;
+
+        let mut my_string = "name=____ año".to_owned();
+        let mut guard = my_string.checked_bytes_guard();
+        let start = guard.savepoint();
+        write!(guard.field(5..9), "Zoë").unwrap();
+        assert_eq!(&guard[5..9], "Zoë".as_bytes());
+        assert!(guard.overwrite_str_at(11, "n").is_err());
+        guard.rollback_to(start);
+        guard.range_mut(5..9).copy_from_slice(b"Ada!");
+        guard.overwrite_str_at(10, "Año").unwrap();
+        guard.commit().unwrap();
+        assert_eq!(my_string, "name=Ada! Año");
+    }
+
+    #[test]
+    fn early_return() {
+        fn edit(s: &mut str) -> Result<(), Error> {
+            let mut guard = s.checked_bytes_guard();
+            guard.write_at(0, b"\xff");
+            guard.commit()?;
+            unreachable!()
+        }
+        let mut my_string = "Hello".to_owned();
+        assert!(matches!(edit(&mut my_string), Err(Error::InvalidUtf8(_))));
+        assert_eq!(my_string, "Hello");
+    }
+}
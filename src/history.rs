@@ -0,0 +1,217 @@
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::{Deref, Range};
+
+use crate::{Error, MutableStringBytes, WithCheckedBytes};
+
+/// Number of edits a [`CheckedString`] can undo unless configured otherwise.
+pub const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+/// The bytes that differ between two versions of a string, enough to move in either direction
+#[derive(Debug, Clone)]
+struct Delta {
+    ranges: Vec<Range<usize>>,
+    /// Contents of each range before the edit, concatenated
+    before: Vec<u8>,
+    /// Contents of each range after the edit, concatenated
+    after: Vec<u8>,
+}
+
+impl Delta {
+    /// Record the changes made in `view`, or `None` if nothing changed.
+    fn capture(view: &MutableStringBytes) -> Option<Self> {
+        let ranges = view.changes().into_ranges();
+        if ranges.is_empty() {
+            return None;
+        }
+        let original = view.original().expect("history is only recorded for copied edits");
+        let mut delta = Self {
+            before: Vec::new(),
+            after: Vec::new(),
+            ranges: Vec::new(),
+        };
+        for range in ranges {
+            delta.before.extend_from_slice(&original[range.clone()]);
+            delta.after.extend_from_slice(&view[range.clone()]);
+            delta.ranges.push(range);
+        }
+        Some(delta)
+    }
+
+    /// Write one side of the delta into `target`.
+    fn apply(&self, target: &mut str, forwards: bool) {
+        let bytes = if forwards { &self.after } else { &self.before };
+        // SAFETY: Each side of the delta turns the string into a version that was previously
+        // committed, so the result is valid UTF-8
+        let target = unsafe { target.as_bytes_mut() };
+        let mut pos = 0;
+        for range in &self.ranges {
+            let len = range.len();
+            target[range.clone()].copy_from_slice(&bytes[pos..pos + len]);
+            pos += len;
+        }
+    }
+}
+
+/// A `String` that remembers its checked edits so that they can be undone and redone
+///
+/// Each edit made with [`with_checked_bytes_mut`](Self::with_checked_bytes_mut) is stored as
+/// the bytes it changed, before and after, rather than as a copy of the whole string. Once
+/// the history limit is reached the oldest edit is forgotten.
+#[derive(Debug, Clone)]
+pub struct CheckedString {
+    value: String,
+    undo: VecDeque<Delta>,
+    redo: Vec<Delta>,
+    limit: usize,
+}
+
+impl CheckedString {
+    /// Wrap `value` with an empty history of up to [`DEFAULT_HISTORY_LIMIT`] edits.
+    pub fn new(value: String) -> Self {
+        Self {
+            value,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            limit: DEFAULT_HISTORY_LIMIT,
+        }
+    }
+
+    /// Set the number of edits that can be undone, forgetting the oldest if there are
+    /// already more than that.
+    pub fn with_history_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self.undo.truncate(limit);
+        self
+    }
+
+    /// The current contents.
+    pub fn as_str(&self) -> &str {
+        &self.value
+    }
+
+    /// Unwrap the string, dropping its history.
+    pub fn into_inner(self) -> String {
+        self.value
+    }
+
+    /// Edit the string as if it were a byte array, recording the edit in the history.
+    ///
+    /// This behaves like [`WithCheckedBytes::with_checked_bytes_mut`]. An edit that commits
+    /// and changes the contents can be undone, and clears any edits that could be redone.
+    pub fn with_checked_bytes_mut<R, F>(&mut self, f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+    {
+        let (res, delta) = self.value.with_checked_bytes_mut(|s| {
+            let res = f(s);
+            (res, Delta::capture(s))
+        })?;
+        if let Some(delta) = delta {
+            self.redo.clear();
+            if self.limit > 0 {
+                if self.undo.len() == self.limit {
+                    self.undo.pop_back();
+                }
+                self.undo.push_front(delta);
+            }
+        }
+        Ok(res)
+    }
+
+    /// Revert the most recent edit, returning `false` if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(delta) = self.undo.pop_front() else {
+            return false;
+        };
+        delta.apply(&mut self.value, false);
+        self.redo.push(delta);
+        true
+    }
+
+    /// Reapply the most recently undone edit, returning `false` if there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(delta) = self.redo.pop() else {
+            return false;
+        };
+        delta.apply(&mut self.value, true);
+        self.undo.push_front(delta);
+        true
+    }
+
+    /// Whether there is an edit that can be undone.
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Whether there is an edit that can be redone.
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Forget every edit, keeping the current contents.
+    pub fn clear_history(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+impl Default for CheckedString {
+    fn default() -> Self {
+        Self::new(String::new())
+    }
+}
+
+impl From<String> for CheckedString {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl Deref for CheckedString {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_and_redo() {
+        let mut my_string = CheckedString::new("Hello wörld".to_owned());
+        my_string.with_checked_bytes_mut(|s| s.set(0, b'J')).unwrap();
+        my_string.with_checked_bytes_mut(|s| s.write_at(7, "ó".as_bytes())).unwrap();
+        my_string.with_checked_bytes_mut(|s| s.set(0, 0xff)).unwrap_err();
+        assert_eq!(&*my_string, "Jello wórld");
+
+        assert!(my_string.undo());
+        assert_eq!(&*my_string, "Jello wörld");
+        assert!(my_string.undo());
+        assert_eq!(&*my_string, "Hello wörld");
+        assert!(!my_string.undo());
+
+        assert!(my_string.redo());
+        assert_eq!(&*my_string, "Jello wörld");
+        my_string.with_checked_bytes_mut(|s| s.set(1, b'a')).unwrap();
+        assert!(!my_string.can_redo());
+        assert!(my_string.undo());
+        assert_eq!(&*my_string, "Jello wörld");
+    }
+
+    #[test]
+    fn bounded_history() {
+        let mut my_string = CheckedString::new("abc".to_owned()).with_history_limit(2);
+        for (i, byte) in [b'x', b'y', b'z'].into_iter().enumerate() {
+            my_string.with_checked_bytes_mut(|s| s.set(i, byte)).unwrap();
+        }
+        // Edits that change nothing are not recorded
+        my_string.with_checked_bytes_mut(|s| s.set(0, b'x')).unwrap();
+        while my_string.undo() {}
+        assert_eq!(&*my_string, "xbc");
+    }
+}
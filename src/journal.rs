@@ -0,0 +1,220 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+use core::str::Utf8Error;
+
+use crate::{validate, Error, InvalidUtf8Error};
+
+/// Undo journal of the bytes overwritten in a buffer
+///
+/// Each entry records the previous contents of a range, so rolling back applies the entries in
+/// reverse order. A journal position returned by [`mark`](Self::mark) can be rolled back to
+/// without undoing anything recorded before it.
+///
+/// Every mark is identified by a generation that is never reused. Rolling back to a mark
+/// forgets the marks made after it, and clearing the journal forgets them all, so a mark
+/// that no longer describes the journal is rejected even once it has grown back past it.
+#[derive(Default)]
+pub(crate) struct Journal {
+    entries: Vec<Range<usize>>,
+    saved: Vec<u8>,
+    /// Number of entries at the most recent mark
+    epoch: usize,
+    /// Index of the most recent entry that saved the whole buffer
+    snapshot: Option<usize>,
+    /// Generations of the marks that can still be rolled back to, oldest first
+    marks: Vec<u64>,
+    /// The generation of the next mark
+    generation: u64,
+}
+
+impl Journal {
+    /// Save the current contents of `range` in `buf` before it is overwritten.
+    pub(crate) fn record(&mut self, buf: &[u8], range: Range<usize>) {
+        // Once the whole buffer has been saved since the last mark, rolling back to that mark
+        // will restore everything anyway
+        if range.is_empty() || self.snapshot.is_some_and(|i| i >= self.epoch) {
+            return;
+        }
+        self.saved.extend_from_slice(&buf[range.clone()]);
+        self.entries.push(range);
+    }
+
+    /// Save the whole of `buf` before it is overwritten.
+    pub(crate) fn record_all(&mut self, buf: &[u8]) {
+        if buf.is_empty() || self.snapshot.is_some_and(|i| i >= self.epoch) {
+            return;
+        }
+        self.record(buf, 0..buf.len());
+        self.snapshot = Some(self.entries.len() - 1);
+    }
+
+    /// Mark the current position so that it can be rolled back to later, returning the
+    /// position and its generation.
+    pub(crate) fn mark(&mut self) -> (usize, u64) {
+        self.epoch = self.entries.len();
+        let generation = self.generation;
+        self.generation += 1;
+        self.marks.push(generation);
+        (self.epoch, generation)
+    }
+
+    /// Forget every mark made after the one with `generation`.
+    ///
+    /// Panics if that mark has already been forgotten.
+    pub(crate) fn release_after(&mut self, generation: u64) {
+        match self.marks.binary_search(&generation) {
+            Ok(index) => self.marks.truncate(index + 1),
+            Err(_) => panic!("savepoint is no longer valid"),
+        }
+    }
+
+    /// Restore every byte of `buf` recorded since `mark`, newest first.
+    ///
+    /// Panics if the journal has been rolled back past the mark with `generation` or cleared
+    /// since it was made.
+    pub(crate) fn rollback_to(&mut self, buf: &mut [u8], mark: usize, generation: u64) {
+        self.release_after(generation);
+        self.undo(buf, mark);
+    }
+
+    fn undo(&mut self, buf: &mut [u8], mark: usize) {
+        while self.entries.len() > mark {
+            let range = self.entries.pop().unwrap();
+            let saved_start = self.saved.len() - range.len();
+            buf[range].copy_from_slice(&self.saved[saved_start..]);
+            self.saved.truncate(saved_start);
+        }
+        self.epoch = self.epoch.min(mark);
+        if self.snapshot.is_some_and(|i| i >= mark) {
+            self.snapshot = self.entries.iter().rposition(|r| r.len() == buf.len());
+        }
+    }
+
+    /// Restore every byte of `buf` recorded in the journal, then clear it.
+    pub(crate) fn rollback(&mut self, buf: &mut [u8]) {
+        self.undo(buf, 0);
+        self.clear();
+    }
+
+    /// Forget every entry but keep the marks, for when the buffer has been restored to how it
+    /// was before anything was recorded.
+    pub(crate) fn forget_entries(&mut self) {
+        self.entries.clear();
+        self.saved.clear();
+        self.epoch = 0;
+        self.snapshot = None;
+    }
+
+    /// Forget everything recorded so far, including every mark.
+    pub(crate) fn clear(&mut self) {
+        self.forget_entries();
+        self.marks.clear();
+    }
+
+    /// Sorted, non-overlapping ranges of `buf` that may differ from before anything was recorded.
+    pub(crate) fn changed_ranges(&self, buf: &[u8]) -> Vec<Range<usize>> {
+        let first_snapshot = self.entries.iter().position(|r| r.len() == buf.len());
+        let mut ranges = match first_snapshot {
+            Some(index) => {
+                // Anything could have changed since the snapshot, so compare against it
+                let mut ranges = self.entries[..index].to_vec();
+                let snapshot_start = ranges.iter().map(|r| r.len()).sum::<usize>();
+                let snapshot = &self.saved[snapshot_start..snapshot_start + buf.len()];
+                ranges.extend(validate::changed_runs(snapshot, buf, 0..buf.len()));
+                ranges
+            }
+            None => self.entries.clone(),
+        };
+        ranges.sort_unstable_by_key(|r| r.start);
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        merged
+    }
+}
+
+/// Bytes of a string being edited in place, with an undo journal of everything overwritten
+///
+/// The target may temporarily hold invalid UTF-8. Any changes that have not been committed are
+/// rolled back when this is dropped. The string is only valid again once the borrow ends
+/// because every one of these is owned by a crate stack frame that drops it, and safe code
+/// has no way to move it anywhere it could be leaked.
+pub(crate) struct InPlace<'a> {
+    target: &'a mut [u8],
+    journal: Journal,
+}
+
+impl<'a> InPlace<'a> {
+    /// Start editing `target`, which must contain valid UTF-8.
+    pub(crate) fn new(target: &'a mut [u8]) -> Self {
+        Self {
+            target,
+            journal: Journal::default(),
+        }
+    }
+
+    pub(crate) fn bytes(&self) -> &[u8] {
+        self.target
+    }
+
+    /// Record the current contents of `range` and return it for writing.
+    pub(crate) fn write(&mut self, range: Range<usize>) -> &mut [u8] {
+        self.journal.record(self.target, range.clone());
+        &mut self.target[range]
+    }
+
+    /// Record the entire contents and return them for writing.
+    pub(crate) fn write_all(&mut self) -> &mut [u8] {
+        self.journal.record_all(self.target);
+        self.target
+    }
+
+    pub(crate) fn mark(&mut self) -> (usize, u64) {
+        self.journal.mark()
+    }
+
+    pub(crate) fn rollback_to(&mut self, mark: usize, generation: u64) {
+        self.journal.rollback_to(self.target, mark, generation);
+    }
+
+    /// Sorted, non-overlapping ranges that have been written since the last commit.
+    pub(crate) fn changed_ranges(&self) -> Vec<Range<usize>> {
+        self.journal.changed_ranges(self.target)
+    }
+
+    /// Check whether the edited bytes are valid UTF-8.
+    pub(crate) fn check(&self) -> Result<(), Utf8Error> {
+        validate::validate_changed(self.target, self.journal.changed_ranges(self.target))
+    }
+
+    /// Validate the edited bytes. If they are valid UTF-8 the journal is discarded and the
+    /// changes become permanent, otherwise the changes are rolled back.
+    pub(crate) fn commit(&mut self) -> Result<(), Error> {
+        match self.check() {
+            Ok(()) => {
+                self.journal.clear();
+                Ok(())
+            }
+            Err(e) => {
+                let bytes = self.target.to_vec();
+                self.rollback();
+                Err(Error::InvalidUtf8(InvalidUtf8Error::new(bytes, e)))
+            }
+        }
+    }
+
+    /// Restore every byte recorded in the journal.
+    pub(crate) fn rollback(&mut self) {
+        self.journal.rollback(self.target);
+    }
+}
+
+impl<'a> Drop for InPlace<'a> {
+    fn drop(&mut self) {
+        self.rollback();
+    }
+}
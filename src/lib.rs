@@ -5,16 +5,19 @@
 //! # Examples:
 //! 
 //! ```
+//! # #[cfg(feature = "alloc")] {
 //! use with_checked_bytes::WithCheckedBytes;
-//! 
+//!
 //! let mut my_string = String::from("hello");
 //! my_string.with_checked_bytes_mut(|s| {
 //!     s[1] += 1;
 //! }).unwrap();
 //! assert_eq!(my_string, "hfllo");
+//! # }
 //! ```
 //! 
 //! ```
+//! # #[cfg(feature = "alloc")] {
 //! # use with_checked_bytes::WithCheckedBytes;
 //! let mut my_string = String::from("hello");
 //! let old_value = my_string.with_checked_bytes_mut(|s| {
@@ -22,20 +25,139 @@
 //! }).unwrap();
 //! assert_eq!(old_value, b'l');
 //! assert_eq!(my_string, "helzo");
+//! # }
 //! ```
 //! 
 //! ```should_panic
+//! # #[cfg(not(feature = "alloc"))] panic!("requires the alloc feature");
+//! # #[cfg(feature = "alloc")] {
 //! # use with_checked_bytes::WithCheckedBytes;
 //! let mut my_string = String::from("hello");
 //! my_string.with_checked_bytes_mut(|s| {
 //!     s[1] = 0xff; // not valid UTF-8
 //! }).unwrap(); // will panic - original string remains unmodified
+//! # }
+//! ```
+//!
+//! A `String` can also be edited as a growable byte vector, so long as the result is valid UTF-8:
+//!
+//! ```
+//! # #[cfg(feature = "alloc")] {
+//! use with_checked_bytes::WithCheckedVec;
+//!
+//! let mut my_string = String::from("hello");
+//! my_string.with_checked_vec_mut(|s| {
+//!     s.truncate(4);
+//!     s.extend_from_slice("ö!".as_bytes());
+//! }).unwrap();
+//! assert_eq!(my_string, "hellö!");
+//! # }
+//! ```
+//!
+//! # Features
+//!
+//! The `std` feature is enabled by default. Without it the crate is `no_std`, and the `alloc`
+//! feature enables everything that needs a heap. The `compact_str`, `smol_str`, `arrayvec`
+//! and `heapless` features implement the traits for the string types from those crates.
+//! The `simd` feature validates edits with SIMD instructions where the CPU supports them,
+//! which is faster for large strings. With `std`, `MutableStringBytes::cursor` and
+//! `MutableStringVec` work with the `std::io` traits. The `unicode-width` feature allows a
+//! `RecordLayout` to be measured in terminal columns.
+//! With neither `std` nor `alloc`, strings can still be edited by supplying a scratch buffer
+//! for the copy:
+//!
 //! ```
+//! # use with_checked_bytes::WithCheckedBytes;
+//! let mut my_string = String::from("hello");
+//! my_string.with_checked_bytes_stack_mut::<16, _, _>(|s| {
+//!     s[0] = b'j';
+//! }).unwrap();
+//! assert_eq!(my_string, "jello");
+//! ```
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
-use std::ops::{Deref, DerefMut};
+mod ascii;
+#[cfg(feature = "alloc")]
+mod batch;
+mod bytes;
+mod chars;
+#[cfg(feature = "alloc")]
+mod changes;
+#[cfg(feature = "std")]
+mod cursor;
+mod error;
+mod field;
+#[cfg(any(feature = "compact_str", feature = "smol_str", feature = "arrayvec", feature = "heapless"))]
+mod foreign;
+#[cfg(feature = "alloc")]
+mod guard;
+#[cfg(feature = "alloc")]
+mod history;
+#[cfg(feature = "alloc")]
+mod journal;
+#[cfg(feature = "alloc")]
+mod observed;
+#[cfg(feature = "alloc")]
+mod owned;
+#[cfg(feature = "alloc")]
+mod pointers;
+#[cfg(feature = "alloc")]
+mod record;
+#[cfg(feature = "alloc")]
+mod strict;
+mod validate;
+pub mod validator;
+#[cfg(feature = "alloc")]
+mod vec;
+
+pub use ascii::AsciiByte;
+#[cfg(feature = "alloc")]
+pub use batch::WithCheckedBatch;
+pub use bytes::MutableStringBytes;
+#[cfg(feature = "alloc")]
+pub use bytes::Savepoint;
+#[cfg(feature = "alloc")]
+pub use changes::ChangeSet;
+#[cfg(feature = "std")]
+pub use cursor::StringBytesCursor;
+#[cfg(feature = "alloc")]
+pub use error::BatchError;
+pub use error::{Error, InvalidUtf8Error, Rejection, TryError};
+pub use field::{FieldWriter, Overflow};
+#[cfg(feature = "alloc")]
+pub use guard::{CheckedBytesGuard, DropPolicy};
+#[cfg(feature = "alloc")]
+pub use history::{CheckedString, DEFAULT_HISTORY_LIMIT};
+#[cfg(feature = "alloc")]
+pub use observed::{CommitEvent, CommitObserver, ObservedString};
+#[cfg(feature = "alloc")]
+pub use owned::OwnedStringBytes;
+#[cfg(feature = "alloc")]
+pub use record::{Align, FieldKey, Record, RecordLayout, Unit};
+#[cfg(feature = "alloc")]
+pub use strict::{StrictStringBytes, WriteProvenance};
+pub use validator::Validator;
+#[cfg(feature = "alloc")]
+pub use vec::{MutableStringVec, WithCheckedVec};
+
+mod sealed {
+    /// Implemented for every type that implements [`WithCheckedBytes`](crate::WithCheckedBytes)
+    pub trait Sealed {}
+}
 
 /// Extension trait for safely editing mutable UTF-8 strings as bytes
-pub trait WithCheckedBytes {
+///
+/// This is implemented for `str`, and therefore `String`, as well as `Box<str>`,
+/// `Cow<'_, str>`, `Rc<str>` and `Arc<str>`. A borrowed `Cow` or a shared `Rc` or `Arc` is
+/// only copied if the edit actually changes it.
+///
+/// The trait is sealed, so that methods which need the `alloc` feature can be added without
+/// breaking implementations elsewhere.
+pub trait WithCheckedBytes: sealed::Sealed {
     /// Edit a mutable `String` or `&mut str` as if it were a byte array.
     /// 
     /// The provided closure will be executed with a mutable view of the String.
@@ -45,71 +167,295 @@ pub trait WithCheckedBytes {
     /// If the buffer contains valid UTF-8, the original string will be overwritten
     /// with the buffer's contents. Any value returned from the closure will be
     /// passed back to the caller.
+    /// 
+    /// Only the regions of the buffer that were modified are validated and copied back,
+    /// so small edits to large strings are cheap. See [`MutableStringBytes`] for how
+    /// modifications are tracked.
+    #[cfg(feature = "alloc")]
     fn with_checked_bytes_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, Error>
     where
         F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R;
-}
 
-impl WithCheckedBytes for str {
-    fn with_checked_bytes_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, Error>
+    /// Edit a mutable `String` or `&mut str` as bytes, reporting which bytes were modified.
+    /// 
+    /// This behaves like [`with_checked_bytes_mut`](Self::with_checked_bytes_mut), but also
+    /// returns the [`ChangeSet`] that was committed, which is empty if the closure left the
+    /// contents as they were. In that case the string is not written to at all.
+    #[cfg(feature = "alloc")]
+    fn with_checked_bytes_tracked_mut<'a, R, F>(&'a mut self, f: F) -> Result<(R, ChangeSet), Error>
     where
         F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
     {
-        let mut target = MutableStringBytes::Borrowed(self.as_bytes());
-        let res = f(&mut target);
-        match target {
-            MutableStringBytes::Borrowed(_) => (),
-            MutableStringBytes::Owned(v) => match std::str::from_utf8(&v) {
-                // SAFETY: We just proved that the new slice content is valid UTF-8
-                Ok(s) => unsafe { self.as_bytes_mut().copy_from_slice(s.as_bytes()) },
-                Err(_) => return Err(Error::InvalidUtf8),
-            },
-        }
-        Ok(res)
+        self.with_checked_bytes_mut(|s| {
+            let res = f(s);
+            (res, s.changes())
+        })
     }
-}
 
-/// Mutable view into a string's content expressed as bytes
-pub enum MutableStringBytes<'a> {
-    Borrowed(&'a [u8]),
-    Owned(Vec<u8>),
-}
+    /// Edit a mutable `String` or `&mut str` as bytes in place, without copying it first.
+    /// 
+    /// This behaves like [`with_checked_bytes_mut`](Self::with_checked_bytes_mut), except
+    /// that writes go directly into the string while the original value of every overwritten
+    /// byte is saved in an undo journal. If the result isn't valid UTF-8, or the closure
+    /// panics, the saved bytes are restored.
+    /// 
+    /// Memory use and copying are proportional to the number of bytes written through
+    /// [`MutableStringBytes::set`], [`write_at`](MutableStringBytes::write_at) and
+    /// [`range_mut`](MutableStringBytes::range_mut). Mutable access through `DerefMut`
+    /// saves the whole string instead.
+    /// 
+    /// The default implementation simply calls `with_checked_bytes_mut`.
+    #[cfg(feature = "alloc")]
+    fn with_checked_bytes_in_place_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+    {
+        self.with_checked_bytes_mut(f)
+    }
+
+    /// Edit a mutable `String` or `&mut str` as bytes, using `scratch` instead of the heap
+    /// for the copy.
+    /// 
+    /// This behaves like `with_checked_bytes_mut` but never allocates. Only the first
+    /// `self.len()` bytes of `scratch` are used. If `scratch` is shorter than the string,
+    /// [`Error::BufferTooSmall`] is returned without calling `f`.
+    fn with_checked_bytes_in_mut<'a, R, F>(&'a mut self, scratch: &mut [u8], f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R;
+
+    /// Edit a mutable `String` or `&mut str` as bytes, using an `N` byte buffer on the
+    /// stack for the copy.
+    /// 
+    /// See [`with_checked_bytes_in_mut`](Self::with_checked_bytes_in_mut).
+    fn with_checked_bytes_stack_mut<'a, const N: usize, R, F>(&'a mut self, f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+    {
+        let mut scratch = [0u8; N];
+        self.with_checked_bytes_in_mut(&mut scratch, f)
+    }
+
+    /// Edit a mutable `String` or `&mut str` as bytes, repairing any invalid UTF-8 instead
+    /// of rejecting the edit.
+    /// 
+    /// After the closure returns, every byte that is part of an invalid sequence is replaced
+    /// with `substitute` and the result is always committed. The closure's return value is
+    /// passed back along with the number of bytes that were replaced.
+    /// 
+    /// Panics if `substitute` is not an ASCII byte.
+    #[cfg(feature = "alloc")]
+    fn with_checked_bytes_lossy_mut<'a, R, F>(&'a mut self, substitute: u8, f: F) -> (R, usize)
+    where
+        F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+    {
+        self.with_checked_bytes_mut(|s| {
+            let res = f(s);
+            let replaced = s.replace_invalid(substitute);
+            (res, replaced)
+        })
+        .expect("repaired bytes are valid UTF-8")
+    }
 
-impl<'a> Deref for MutableStringBytes<'a> {
-    type Target = [u8];
+    /// Edit a mutable `String` or `&mut str` as bytes with a closure that may fail.
+    /// 
+    /// The string is only modified if the closure returns `Ok` and the buffer contains valid
+    /// UTF-8. If the closure returns `Err`, every change it made is discarded and the error is
+    /// passed back as [`TryError::Closure`]. Failure to commit the changes is reported as
+    /// [`TryError::Checked`].
+    #[cfg(feature = "alloc")]
+    fn try_with_checked_bytes_mut<'a, R, E, F>(&'a mut self, f: F) -> Result<R, TryError<E>>
+    where
+        F: for<'b> FnOnce(&'b mut MutableStringBytes) -> Result<R, E>,
+    {
+        let res = self.with_checked_bytes_mut(|s| {
+            let res = f(s);
+            if res.is_err() {
+                s.discard();
+            }
+            res
+        })?;
+        res.map_err(TryError::Closure)
+    }
 
-    fn deref(&self) -> &Self::Target {
-        match self {
-            Self::Borrowed(slice) => *slice,
-            Self::Owned(vec) => vec.as_slice(),
+    /// Edit a mutable `String` or `&mut str` as bytes, requiring the result to satisfy
+    /// `validator` as well as being valid UTF-8.
+    /// 
+    /// This behaves like [`with_checked_bytes_mut`](Self::with_checked_bytes_mut), except
+    /// that once the buffer is known to be valid UTF-8 it is also passed to the validator as
+    /// a whole. If the validator rejects it, the original string is not modified and
+    /// [`Error::Rejected`] is returned. See the [`validator`] module for the built-in rules.
+    #[cfg(feature = "alloc")]
+    fn with_validated_bytes_mut<'a, V, R, F>(&'a mut self, validator: V, f: F) -> Result<R, Error>
+    where
+        V: Validator,
+        F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+    {
+        let (res, rejected) = self.with_checked_bytes_mut(|s| {
+            let res = f(s);
+            let rejected = match s.to_str() {
+                Ok(text) => validator.validate(text).err(),
+                // Reported when the changes are committed
+                Err(_) => None,
+            };
+            if rejected.is_some() {
+                s.discard();
+            }
+            (res, rejected)
+        })?;
+        match rejected {
+            Some(rejection) => Err(Error::Rejected(rejection)),
+            None => Ok(res),
         }
     }
-}
 
-impl<'a> DerefMut for MutableStringBytes<'a> {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        if let Self::Borrowed(slice) = self {
-            let v = slice.to_vec();
-            let _ = std::mem::replace(self, Self::Owned(v));
+    /// Edit a mutable `String` or `&mut str` as bytes, requiring the bytes in `protected` to
+    /// remain unchanged.
+    ///
+    /// This behaves like [`with_checked_bytes_mut`](Self::with_checked_bytes_mut), except that
+    /// if any byte within the protected ranges differs from the original when the closure
+    /// returns, the original string is not modified and [`Error::Protected`] reports the first
+    /// such byte. Protected bytes may be written so long as they end up with their original
+    /// values. This is checked before the result is validated as UTF-8.
+    ///
+    /// Panics if a protected range is out of bounds.
+    #[cfg(feature = "alloc")]
+    fn with_checked_bytes_protected_mut<'a, R, F>(&'a mut self, protected: &[core::ops::Range<usize>], f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+    {
+        let (res, violation) = self.with_checked_bytes_mut(|s| {
+            for range in protected {
+                assert!(
+                    range.end <= s.len(),
+                    "protected range {:?} is out of bounds for a string of {} bytes",
+                    range,
+                    s.len()
+                );
+            }
+            let res = f(s);
+            let violation = s
+                .changes()
+                .into_iter()
+                .flat_map(|changed| {
+                    protected
+                        .iter()
+                        .filter(move |p| p.start < changed.end && changed.start < p.end)
+                        .map(move |p| p.start.max(changed.start))
+                })
+                .min();
+            if violation.is_some() {
+                s.discard();
+            }
+            (res, violation)
+        })?;
+        match violation {
+            Some(index) => Err(Error::Protected { index }),
+            None => Ok(res),
         }
-        match self {
-            Self::Borrowed(_) => unreachable!(),
-            Self::Owned(vec) => vec.as_mut_slice(),
+    }
+
+    /// Edit a mutable `String` or `&mut str` as bytes, keeping track of every write so that
+    /// an invalid result can be traced back to its cause.
+    ///
+    /// This behaves like [`with_checked_bytes_mut`](Self::with_checked_bytes_mut), except that
+    /// the buffer can only be modified through the methods of [`StrictStringBytes`], which
+    /// record the bytes written and the caller's location. If the result is not valid UTF-8,
+    /// [`Error::InvalidWrite`] identifies the last write that touched the first invalid
+    /// sequence. This costs a little memory per write, so it is best suited to debugging.
+    #[cfg(feature = "alloc")]
+    fn with_checked_bytes_strict_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, Error>
+    where
+        F: for<'v, 'b> FnOnce(&mut StrictStringBytes<'v, 'b>) -> R,
+    {
+        let (res, blame) = self.with_checked_bytes_mut(|s| {
+            let mut strict = StrictStringBytes::new(s);
+            let res = f(&mut strict);
+            let blame = strict.blame();
+            if blame.is_some() {
+                strict.discard();
+            }
+            (res, blame)
+        })?;
+        match blame {
+            Some(err) => Err(err),
+            None => Ok(res),
         }
     }
-}
 
-/// Errors that can occur while mutating strings
-#[derive(Debug)]
-pub enum Error {
-    InvalidUtf8,
+    /// Start an edit session over a mutable `String` or `&mut str` as bytes.
+    /// 
+    /// This is an alternative to [`with_checked_bytes_mut`](Self::with_checked_bytes_mut)
+    /// for when a closure is awkward. The returned guard edits the string through the same
+    /// kind of [`MutableStringBytes`] view, and the string is only modified when the changes are
+    /// committed with [`CheckedBytesGuard::commit`]. By default, uncommitted changes are
+    /// discarded when the guard is dropped; see [`DropPolicy`].
+    #[cfg(feature = "alloc")]
+    fn checked_bytes_guard(&mut self) -> CheckedBytesGuard<'_>;
+
+    /// Edit an ASCII `String` or `&mut str` as a slice of [`AsciiByte`].
+    /// 
+    /// The string is checked once to make sure it only contains ASCII, returning
+    /// [`Error::NotAscii`] if not. Since only ASCII bytes can be written through the slice,
+    /// the result is always valid UTF-8 and no validation is needed afterwards.
+    fn with_ascii_bytes_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut [AsciiByte]) -> R;
 }
 
-impl std::error::Error for Error {}
+impl sealed::Sealed for str {}
+
+impl WithCheckedBytes for str {
+    #[cfg(feature = "alloc")]
+    fn with_checked_bytes_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+    {
+        let mut target = MutableStringBytes::new(self);
+        let res = f(&mut target);
+        target.commit()?;
+        Ok(res)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn with_checked_bytes_in_place_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+    {
+        // SAFETY: The view rolls back any changes that are not committed when it is dropped,
+        // including when `f` panics. `f` can't move it out of this frame to avoid that, since
+        // safe code can only own a `MutableStringBytes` inside a `CheckedBytesGuard`, which
+        // never hands out a mutable reference to swap with. So the string holds valid UTF-8
+        // again by the time this borrow ends
+        let mut target = MutableStringBytes::in_place(unsafe { self.as_bytes_mut() });
+        let res = f(&mut target);
+        target.commit()?;
+        Ok(res)
+    }
+
+    fn with_checked_bytes_in_mut<'a, R, F>(&'a mut self, scratch: &mut [u8], f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+    {
+        let (needed, available) = (self.len(), scratch.len());
+        if available < needed {
+            return Err(Error::BufferTooSmall { needed, available });
+        }
+        let mut target = MutableStringBytes::with_buffer(self, &mut scratch[..needed]);
+        let res = f(&mut target);
+        target.commit()?;
+        Ok(res)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn checked_bytes_guard(&mut self) -> CheckedBytesGuard<'_> {
+        CheckedBytesGuard::new(MutableStringBytes::new(self))
+    }
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "MutableStringBytes contains invalid UTF-8 after modifications")
+    fn with_ascii_bytes_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut [AsciiByte]) -> R,
+    {
+        Ok(f(ascii::ascii_bytes_mut(self)?))
     }
 }
 
@@ -117,6 +463,7 @@ impl std::fmt::Display for Error {
 mod tests {
     use super::*;
 
+    #[cfg(feature = "alloc")]
     #[test]
     fn twiddle_a_byte() {
         let mut my_string = "Hello".to_owned();
@@ -126,6 +473,7 @@ mod tests {
         assert_eq!(my_string, "Helmo");
     }
 
+    #[cfg(feature = "alloc")]
     #[test]
     fn twiddle_a_byte_bad() {
         let mut my_string = "Hello".to_owned();
@@ -135,6 +483,299 @@ mod tests {
         assert_eq!(my_string, "Hello");
     }
 
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn invalid_utf8_details() {
+        let mut my_string = "Hello".to_owned();
+        let err = my_string.with_checked_bytes_mut(|s| {
+            s[1] = b'a';
+            s[3] = 0xff;
+        }).unwrap_err();
+        let Error::InvalidUtf8(details) = &err else { unreachable!() };
+        assert_eq!(details.valid_up_to(), 3);
+        assert_eq!(details.error_len(), Some(1));
+        assert!(std::error::Error::source(&err).is_some());
+        let Error::InvalidUtf8(details) = err else { unreachable!() };
+        assert_eq!(details.into_bytes(), b"Hal\xffo");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn tracked_writes() {
+        let mut my_string = "Hello wörld".to_owned();
+        my_string.with_checked_bytes_mut(|s| {
+            s.set(0, b'J');
+            s.write_at(7, "ü".as_bytes());
+            s.range_mut(11..)[0] = b'D';
+        }).unwrap();
+        assert_eq!(my_string, "Jello würlD");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn tracked_write_splits_char() {
+        let mut my_string = "Hello wörld".to_owned();
+        my_string.with_checked_bytes_mut(|s| {
+            s.set(8, b'o');
+        }).unwrap_err();
+        assert_eq!(my_string, "Hello wörld");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn in_place_edit() {
+        let mut my_string = "Hello wörld".to_owned();
+        my_string.with_checked_bytes_in_place_mut(|s| {
+            s.write_at(7, "ü".as_bytes());
+            s[0] = b'J';
+            s.set(11, b'D');
+        }).unwrap();
+        assert_eq!(my_string, "Jello würlD");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn in_place_rollback() {
+        let mut my_string = "Hello wörld".to_owned();
+        let err = my_string.with_checked_bytes_in_place_mut(|s| {
+            s.set(0, b'J');
+            s.set(8, b'o');
+            s.set(0, b'Y');
+        }).unwrap_err();
+        assert_eq!(my_string, "Hello wörld");
+        let Error::InvalidUtf8(details) = err else { unreachable!() };
+        assert_eq!(details.as_bytes(), b"Yello w\xc3o\x72ld".as_slice());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn in_place_rollback_on_panic() {
+        let mut my_string = "Hello wörld".to_owned();
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            my_string.with_checked_bytes_in_place_mut(|s| {
+                s.set(8, b'o');
+                panic!("edit failed");
+            })
+        }));
+        assert!(res.is_err());
+        assert_eq!(my_string, "Hello wörld");
+    }
+
+    #[test]
+    fn buffer_edit() {
+        let mut my_string = "Hello wörld".to_owned();
+        let mut scratch = [0u8; 32];
+        my_string.with_checked_bytes_in_mut(&mut scratch, |s| {
+            s.write_at(7, "ü".as_bytes());
+        }).unwrap();
+        assert_eq!(my_string, "Hello würld");
+        my_string.with_checked_bytes_stack_mut::<12, _, _>(|s| {
+            s[8] = b'x';
+        }).unwrap_err();
+        assert_eq!(my_string, "Hello würld");
+    }
+
+    #[test]
+    fn buffer_edit_invalid() {
+        let mut my_string = "Hello".to_owned();
+        let err = my_string.with_checked_bytes_stack_mut::<8, _, _>(|s| {
+            s[1] = b'a';
+            s.set(3, 0xff);
+        }).unwrap_err();
+        let Error::InvalidUtf8(details) = err else { unreachable!() };
+        assert_eq!(details.valid_up_to(), 3);
+        assert_eq!(details.error_len(), Some(1));
+        assert_eq!(my_string, "Hello");
+    }
+
+    #[test]
+    fn buffer_tracked_writes() {
+        let mut my_string = "Hello wörld".to_owned();
+        let mut scratch = [0u8; 16];
+        let old = my_string.as_mut_str().with_checked_bytes_in_mut(&mut scratch, |s| {
+            s.set(0, b'J');
+            s.write_at(7, "ü".as_bytes());
+            core::mem::replace(&mut s.range_mut(11..)[0], b'D')
+        }).unwrap();
+        assert_eq!(old, b'd');
+        assert_eq!(my_string, "Jello würlD");
+    }
+
+    #[test]
+    fn buffer_too_small() {
+        let mut my_string = "Hello".to_owned();
+        let err = my_string.with_checked_bytes_stack_mut::<4, _, _>(|_| {
+            unreachable!()
+        }).unwrap_err();
+        assert!(matches!(err, Error::BufferTooSmall { needed: 5, available: 4 }));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn lossy_edit() {
+        let mut my_string = "Hello wörld".to_owned();
+        let (_, replaced) = my_string.with_checked_bytes_lossy_mut(b'?', |s| {
+            s[1] = 0xff;
+            s[7] = b'o';
+        });
+        assert_eq!(replaced, 2);
+        assert_eq!(my_string, "H?llo wo?rld");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn try_edit_aborted() {
+        let mut my_string = "Hello".to_owned();
+        let err = my_string.try_with_checked_bytes_mut(|s| {
+            s[0] = b'J';
+            s.get(10).copied().ok_or("too short")
+        }).unwrap_err();
+        assert!(matches!(err, TryError::Closure("too short")));
+        assert_eq!(my_string, "Hello");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn try_edit_invalid() {
+        let mut my_string = "Hello".to_owned();
+        let err = my_string.try_with_checked_bytes_mut(|s| {
+            s[0] = 0xff;
+            Ok::<_, ()>(())
+        }).unwrap_err();
+        assert!(matches!(err, TryError::Checked(Error::InvalidUtf8(_))));
+        my_string.try_with_checked_bytes_mut(|s| {
+            s[0] = b'J';
+            Ok::<_, ()>(())
+        }).unwrap();
+        assert_eq!(my_string, "Jello");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn savepoints() {
+        let mut my_string = "Hello".to_owned();
+        my_string.with_checked_bytes_mut(|s| {
+            let start = s.savepoint();
+            s.set(0, b'J');
+            let jello = s.savepoint();
+            s.set(4, 0xff);
+            assert!(!s.is_valid());
+            s.rollback_to(jello);
+            assert!(s.is_valid());
+            assert_eq!(&s[..], b"Jello");
+            s[1] = b'a';
+            s.rollback_to(start);
+            assert_eq!(&s[..], b"Hello");
+            s.set(4, b'!');
+        }).unwrap();
+        assert_eq!(my_string, "Hell!");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn in_place_savepoints() {
+        let mut my_string = "Hello".to_owned();
+        my_string.with_checked_bytes_in_place_mut(|s| {
+            s.set(0, b'J');
+            let jello = s.savepoint();
+            s[4] = 0xff;
+            s.set(1, b'a');
+            assert!(!s.is_valid());
+            s.rollback_to(jello);
+            assert!(s.is_valid());
+        }).unwrap();
+        assert_eq!(my_string, "Jello");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn stale_savepoint() {
+        fn assert_stale(edit: fn(&mut MutableStringBytes)) {
+            for in_place in [false, true] {
+                let mut my_string = "Hello".to_owned();
+                let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| match in_place {
+                    false => my_string.with_checked_bytes_mut(edit),
+                    true => my_string.with_checked_bytes_in_place_mut(edit),
+                }));
+                let payload = res.unwrap_err();
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .copied()
+                    .or_else(|| payload.downcast_ref::<String>().map(String::as_str));
+                assert_eq!(message, Some("savepoint is no longer valid"));
+                assert_eq!(my_string, "Hello");
+            }
+        }
+
+        // Rolled back past
+        assert_stale(|s| {
+            s.set(0, b'J');
+            let first = s.savepoint();
+            s.set(1, b'a');
+            let second = s.savepoint();
+            s.set(2, b'x');
+            s.rollback_to(first);
+            s.rollback_to(second);
+        });
+        // Rolled back past, then written again so the journal is as long as when it was taken
+        assert_stale(|s| {
+            let start = s.savepoint();
+            s.set(0, b'J');
+            let later = s.savepoint();
+            s.set(1, b'a');
+            s.rollback_to(start);
+            s.set(4, b'!');
+            s.rollback_to(later);
+        });
+        // Taken before every change was discarded
+        assert_stale(|s| {
+            s.set(0, b'J');
+            let before = s.savepoint();
+            s.discard();
+            s.set(1, b'a');
+            s.rollback_to(before);
+        });
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn validated_edit() {
+        use validator::{NoNul, PrintableAscii};
+
+        let mut my_string = "key=value".to_owned();
+        let err = my_string.with_validated_bytes_mut(PrintableAscii.and(NoNul), |s| {
+            s.set(3, b'\n');
+        }).unwrap_err();
+        assert!(matches!(err, Error::Rejected(Rejection { index: 3, .. })));
+        assert_eq!(my_string, "key=value");
+        let err = my_string.with_validated_bytes_mut(PrintableAscii, |s| {
+            s.set(3, 0xff);
+        }).unwrap_err();
+        assert!(matches!(err, Error::InvalidUtf8(_)));
+        my_string.with_validated_bytes_mut(PrintableAscii, |s| {
+            s.set(3, b':');
+        }).unwrap();
+        assert_eq!(my_string, "key:value");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn protected_ranges() {
+        let mut my_string = "HDR1:payload:SIG".to_owned();
+        let err = my_string.with_checked_bytes_protected_mut(&[0..5, 13..16], |s| {
+            s.write_at(5, b"PAYLOAD");
+            s.set(14, b'X');
+            s.set(0, b'H');
+        }).unwrap_err();
+        assert!(matches!(err, Error::Protected { index: 14 }));
+        assert_eq!(my_string, "HDR1:payload:SIG");
+        my_string.with_checked_bytes_protected_mut(&[0..5, 13..16], |s| {
+            s.write_at(3, b"1:PAYLOAD:");
+        }).unwrap();
+        assert_eq!(my_string, "HDR1:PAYLOAD:SIG");
+    }
+
+    #[cfg(feature = "alloc")]
     #[test]
     fn return_a_byte() {
         let mut my_string = "Hello".to_owned();
@@ -144,6 +785,7 @@ mod tests {
         assert_eq!(ascii, 108u8);
     }
 
+    #[cfg(feature = "alloc")]
     #[test]
     fn slice_edit() {
         let mut my_string = "Hello".to_owned();
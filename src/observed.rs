@@ -0,0 +1,193 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Deref;
+
+use crate::{ChangeSet, Error, MutableStringBytes, Rejection};
+
+/// Details of a change to an [`ObservedString`], passed to each [`CommitObserver`]
+#[derive(Debug, Clone, Copy)]
+pub struct CommitEvent<'e> {
+    before: &'e str,
+    after: &'e str,
+    changes: &'e ChangeSet,
+}
+
+impl<'e> CommitEvent<'e> {
+    /// The contents before the edit.
+    pub fn before(&self) -> &'e str {
+        self.before
+    }
+
+    /// The contents after the edit.
+    pub fn after(&self) -> &'e str {
+        self.after
+    }
+
+    /// The byte ranges that differ between the old and new contents.
+    pub fn changes(&self) -> &'e ChangeSet {
+        self.changes
+    }
+}
+
+/// Something that needs to know when an [`ObservedString`] changes
+pub trait CommitObserver {
+    /// Called once an edit is known to be valid UTF-8, before it is applied.
+    ///
+    /// Returning an error vetoes the edit, leaving the string unmodified. The default
+    /// implementation accepts every edit.
+    fn before_commit(&mut self, event: &CommitEvent<'_>) -> Result<(), Rejection> {
+        let _ = event;
+        Ok(())
+    }
+
+    /// Called after an edit has been applied.
+    fn after_commit(&mut self, event: &CommitEvent<'_>);
+}
+
+/// A `String` that notifies observers whenever it is changed through a checked edit
+///
+/// The contents can be read through `Deref`, but can only be modified with
+/// [`with_checked_bytes_mut`](Self::with_checked_bytes_mut), so observers such as caches and
+/// indexes never miss an update. Observers are only notified of edits that actually change
+/// the contents.
+#[derive(Default)]
+pub struct ObservedString {
+    value: String,
+    observers: Vec<Box<dyn CommitObserver>>,
+}
+
+impl ObservedString {
+    /// Wrap `value`, initially with no observers.
+    pub fn new(value: String) -> Self {
+        Self {
+            value,
+            observers: Vec::new(),
+        }
+    }
+
+    /// Register an observer, which is notified after those already registered.
+    pub fn add_observer<O: CommitObserver + 'static>(&mut self, observer: O) {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// The current contents.
+    pub fn as_str(&self) -> &str {
+        &self.value
+    }
+
+    /// Unwrap the string, dropping the observers.
+    pub fn into_inner(self) -> String {
+        self.value
+    }
+
+    /// Edit the string as if it were a byte array.
+    ///
+    /// This behaves like [`WithCheckedBytes::with_checked_bytes_mut`](crate::WithCheckedBytes::with_checked_bytes_mut).
+    /// In addition, if the result is valid UTF-8 and differs from the current contents, every
+    /// observer's [`before_commit`](CommitObserver::before_commit) is called in turn. If any of
+    /// them vetoes the edit, the string is not modified and [`Error::Rejected`] is returned.
+    /// Otherwise the change is applied and every observer's
+    /// [`after_commit`](CommitObserver::after_commit) is called.
+    pub fn with_checked_bytes_mut<R, F>(&mut self, f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+    {
+        let mut view = MutableStringBytes::shared(&self.value);
+        let res = f(&mut view);
+        let changes = view.changes();
+        let edited = match view.into_edited()? {
+            Some(edited) if !changes.is_empty() => edited,
+            _ => return Ok(res),
+        };
+        let event = CommitEvent {
+            before: &self.value,
+            after: &edited,
+            changes: &changes,
+        };
+        for observer in &mut self.observers {
+            observer.before_commit(&event).map_err(Error::Rejected)?;
+        }
+        let old = core::mem::replace(&mut self.value, edited);
+        let event = CommitEvent {
+            before: &old,
+            after: &self.value,
+            changes: &changes,
+        };
+        for observer in &mut self.observers {
+            observer.after_commit(&event);
+        }
+        Ok(res)
+    }
+}
+
+impl From<String> for ObservedString {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl Deref for ObservedString {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl fmt::Debug for ObservedString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ObservedString")
+            .field("value", &self.value)
+            .field("observers", &self.observers.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::rc::Rc;
+    use core::cell::RefCell;
+
+    use super::*;
+
+    struct Log(Rc<RefCell<Vec<String>>>);
+
+    impl CommitObserver for Log {
+        fn after_commit(&mut self, event: &CommitEvent<'_>) {
+            let entry = format!("{} -> {} {:?}", event.before(), event.after(), event.changes().ranges());
+            self.0.borrow_mut().push(entry);
+        }
+    }
+
+    struct NoDigits;
+
+    impl CommitObserver for NoDigits {
+        fn before_commit(&mut self, event: &CommitEvent<'_>) -> Result<(), Rejection> {
+            match event.after().find(|c: char| c.is_ascii_digit()) {
+                Some(index) => Err(Rejection { index, reason: "contains a digit" }),
+                None => Ok(()),
+            }
+        }
+
+        fn after_commit(&mut self, _event: &CommitEvent<'_>) {}
+    }
+
+    #[test]
+    fn notify_and_veto() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut my_string = ObservedString::new("Hello".to_owned());
+        my_string.add_observer(NoDigits);
+        my_string.add_observer(Log(Rc::clone(&log)));
+
+        my_string.with_checked_bytes_mut(|s| s.set(0, b'J')).unwrap();
+        my_string.with_checked_bytes_mut(|s| s.set(0, b'J')).unwrap();
+        let err = my_string.with_checked_bytes_mut(|s| s.set(4, b'0')).unwrap_err();
+        assert!(matches!(err, Error::Rejected(Rejection { index: 4, .. })));
+        my_string.with_checked_bytes_mut(|s| s.set(4, 0xff)).unwrap_err();
+
+        assert_eq!(&*my_string, "Jello");
+        assert_eq!(*log.borrow(), ["Hello -> Jello [0..1]"]);
+    }
+}
@@ -0,0 +1,276 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::{Deref, DerefMut, Range, RangeBounds};
+
+use crate::bytes::resolve_range;
+use crate::{validate, Error, InvalidUtf8Error};
+
+/// An owned byte buffer that started as a `String` and can be turned back into one if it
+/// still holds valid UTF-8
+///
+/// Unlike [`MutableStringBytes`](crate::MutableStringBytes), this is not tied to a closure,
+/// so it can be kept, edited over time and sent between threads. Converting from a `String`
+/// takes over its allocation without copying, and so does converting back.
+///
+/// Modifications are tracked in the same way as `MutableStringBytes`, so checking the buffer
+/// only needs to examine the region that changed. Methods that insert or remove bytes count
+/// everything after that point as changed.
+#[derive(Debug, Clone, Default)]
+pub struct OwnedStringBytes {
+    bytes: Vec<u8>,
+    /// Everything outside this range is unchanged since the buffer was last valid UTF-8
+    dirty: Option<Range<usize>>,
+}
+
+impl OwnedStringBytes {
+    /// Create an empty buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the buffer currently contains valid UTF-8.
+    pub fn is_valid(&self) -> bool {
+        self.check().is_ok()
+    }
+
+    /// Convert the buffer back into a `String` if it contains valid UTF-8.
+    ///
+    /// If it doesn't, the buffer is handed back unchanged along with the error. The error
+    /// holds its own copy of the rejected bytes.
+    pub fn try_into_string(self) -> Result<String, (Self, Error)> {
+        match self.check() {
+            // SAFETY: We just proved that the content is valid UTF-8
+            Ok(()) => Ok(unsafe { String::from_utf8_unchecked(self.bytes) }),
+            Err(e) => {
+                let err = Error::InvalidUtf8(InvalidUtf8Error::from_slice(&self.bytes, e));
+                Err((self, err))
+            }
+        }
+    }
+
+    /// Convert the buffer into a `String`, replacing each invalid sequence with U+FFFD
+    /// REPLACEMENT CHARACTER.
+    pub fn into_string_lossy(self) -> String {
+        match self.check() {
+            // SAFETY: We just proved that the content is valid UTF-8
+            Ok(()) => unsafe { String::from_utf8_unchecked(self.bytes) },
+            Err(_) => String::from_utf8_lossy(&self.bytes).into_owned(),
+        }
+    }
+
+    /// Take the underlying bytes, whether or not they are valid UTF-8.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Overwrite the byte at `index`.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set(&mut self, index: usize, byte: u8) {
+        self.range_mut(index..index + 1)[0] = byte;
+    }
+
+    /// Overwrite the bytes starting at `offset` with the contents of `bytes`.
+    ///
+    /// Panics if the write would extend past the end of the buffer.
+    pub fn write_at(&mut self, offset: usize, bytes: &[u8]) {
+        self.range_mut(offset..offset + bytes.len()).copy_from_slice(bytes);
+    }
+
+    /// Get mutable access to a subrange of the buffer.
+    ///
+    /// Only this range is considered modified. Panics if the range is out of bounds.
+    pub fn range_mut<R: RangeBounds<usize>>(&mut self, range: R) -> &mut [u8] {
+        let range = resolve_range(range, self.bytes.len());
+        self.mark_dirty(range.clone());
+        &mut self.bytes[range]
+    }
+
+    /// Append a byte to the end of the buffer.
+    pub fn push(&mut self, byte: u8) {
+        self.extend_from_slice(&[byte]);
+    }
+
+    /// Remove the last byte from the buffer and return it, or `None` if it is empty.
+    pub fn pop(&mut self) -> Option<u8> {
+        let byte = self.bytes.pop()?;
+        self.changed_from(self.bytes.len());
+        Some(byte)
+    }
+
+    /// Insert a byte at position `index`, shifting all bytes after it to the right.
+    ///
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, byte: u8) {
+        self.bytes.insert(index, byte);
+        self.changed_from(index);
+    }
+
+    /// Remove and return the byte at position `index`, shifting all bytes after it to the left.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> u8 {
+        let byte = self.bytes.remove(index);
+        self.changed_from(index);
+        byte
+    }
+
+    /// Shorten the buffer to `len` bytes. Has no effect if `len` is not less than the current length.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.bytes.len() {
+            self.bytes.truncate(len);
+            self.changed_from(len);
+        }
+    }
+
+    /// Remove all bytes from the buffer.
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Append all bytes in `other` to the end of the buffer.
+    pub fn extend_from_slice(&mut self, other: &[u8]) {
+        let start = self.bytes.len();
+        self.bytes.extend_from_slice(other);
+        self.changed_from(start);
+    }
+
+    /// Get mutable access to the underlying vector. Everything is considered modified.
+    pub fn as_mut_vec(&mut self) -> &mut Vec<u8> {
+        self.changed_from(0);
+        &mut self.bytes
+    }
+
+    fn check(&self) -> Result<(), core::str::Utf8Error> {
+        validate::validate_changed(&self.bytes, self.dirty.clone())
+    }
+
+    fn mark_dirty(&mut self, range: Range<usize>) {
+        self.dirty = Some(match self.dirty.take() {
+            Some(d) => d.start.min(range.start)..d.end.max(range.end),
+            None => range,
+        });
+    }
+
+    /// Record that every byte from `start` to the end may have moved or changed. `start` may
+    /// equal the length, marking a point where bytes were removed.
+    fn changed_from(&mut self, start: usize) {
+        let len = self.bytes.len();
+        if let Some(d) = &mut self.dirty {
+            *d = d.start.min(len)..d.end.min(len);
+        }
+        self.mark_dirty(start..len);
+    }
+}
+
+impl From<String> for OwnedStringBytes {
+    fn from(s: String) -> Self {
+        Self {
+            bytes: s.into_bytes(),
+            dirty: None,
+        }
+    }
+}
+
+impl From<OwnedStringBytes> for Vec<u8> {
+    fn from(buf: OwnedStringBytes) -> Self {
+        buf.into_bytes()
+    }
+}
+
+impl TryFrom<OwnedStringBytes> for String {
+    type Error = (OwnedStringBytes, Error);
+
+    fn try_from(buf: OwnedStringBytes) -> Result<Self, Self::Error> {
+        buf.try_into_string()
+    }
+}
+
+impl Extend<u8> for OwnedStringBytes {
+    fn extend<I: IntoIterator<Item = u8>>(&mut self, iter: I) {
+        let start = self.bytes.len();
+        self.bytes.extend(iter);
+        self.changed_from(start);
+    }
+}
+
+impl<'b> Extend<&'b u8> for OwnedStringBytes {
+    fn extend<I: IntoIterator<Item = &'b u8>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+/// Buffers are equal if they hold the same bytes, regardless of what has been modified.
+impl PartialEq for OwnedStringBytes {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl Eq for OwnedStringBytes {}
+
+impl Deref for OwnedStringBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.bytes
+    }
+}
+
+impl DerefMut for OwnedStringBytes {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.changed_from(0);
+        &mut self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_without_copying() {
+        let my_string = String::from("Hello");
+        let ptr = my_string.as_ptr();
+        let mut buf = OwnedStringBytes::from(my_string);
+        buf.set(0, b'J');
+        let my_string = buf.try_into_string().unwrap();
+        assert_eq!(my_string, "Jello");
+        assert_eq!(my_string.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn invalid_over_time() {
+        let mut buf = OwnedStringBytes::from(String::from("né"));
+        buf.truncate(2);
+        assert!(!buf.is_valid());
+        buf.push(0xa9);
+        buf.push(b'!');
+        assert!(buf.is_valid());
+        buf[0] = 0xff;
+        let (buf, err) = buf.try_into_string().unwrap_err();
+        let Error::InvalidUtf8(details) = err else { unreachable!() };
+        assert_eq!(details.valid_up_to(), 0);
+        assert_eq!(buf.into_string_lossy(), "\u{fffd}é!");
+    }
+
+    #[test]
+    fn insert_and_remove() {
+        let mut buf = OwnedStringBytes::from(String::from("aéb"));
+        buf.remove(1);
+        assert!(!buf.is_valid());
+        buf.insert(1, 0xc3);
+        assert!(buf.is_valid());
+        assert_eq!(String::try_from(buf).unwrap(), "aéb");
+    }
+
+    #[test]
+    fn equality_ignores_changes() {
+        let buf = OwnedStringBytes::from(String::from("a"));
+        let mut edited = buf.clone();
+        edited.set(0, b'a');
+        assert_eq!(buf, edited);
+        edited.set(0, b'b');
+        assert_ne!(buf, edited);
+    }
+}
@@ -0,0 +1,331 @@
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::sync::Arc;
+
+use crate::{AsciiByte, CheckedBytesGuard, Error, MutableStringBytes, WithCheckedBytes};
+
+/// Edit a string that may be shared, returning a new string if anything was changed.
+pub(crate) fn edit_shared<R, F>(s: &str, f: F) -> Result<(R, Option<String>), Error>
+where
+    F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+{
+    let mut target = MutableStringBytes::shared(s);
+    let res = f(&mut target);
+    Ok((res, target.into_edited()?))
+}
+
+/// Edit a string that may be shared using `scratch` for the copy, returning a new string if
+/// anything was changed.
+pub(crate) fn edit_shared_in<R, F>(s: &str, scratch: &mut [u8], f: F) -> Result<(R, Option<String>), Error>
+where
+    F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+{
+    let (needed, available) = (s.len(), scratch.len());
+    if available < needed {
+        return Err(Error::BufferTooSmall { needed, available });
+    }
+    let mut target = MutableStringBytes::shared_with_buffer(s, &mut scratch[..needed]);
+    let res = f(&mut target);
+    Ok((res, target.into_edited()?))
+}
+
+impl crate::sealed::Sealed for Box<str> {}
+
+impl WithCheckedBytes for Box<str> {
+    fn with_checked_bytes_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+    {
+        (**self).with_checked_bytes_mut(f)
+    }
+
+    fn with_checked_bytes_in_place_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+    {
+        (**self).with_checked_bytes_in_place_mut(f)
+    }
+
+    fn with_checked_bytes_in_mut<'a, R, F>(&'a mut self, scratch: &mut [u8], f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+    {
+        (**self).with_checked_bytes_in_mut(scratch, f)
+    }
+
+    fn checked_bytes_guard(&mut self) -> CheckedBytesGuard<'_> {
+        (**self).checked_bytes_guard()
+    }
+
+    fn with_ascii_bytes_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut [AsciiByte]) -> R,
+    {
+        (**self).with_ascii_bytes_mut(f)
+    }
+}
+
+impl crate::sealed::Sealed for Cow<'_, str> {}
+
+/// A borrowed `Cow` only becomes owned if the closure changes it and the result is valid.
+///
+/// [`checked_bytes_guard`](WithCheckedBytes::checked_bytes_guard) can't tell in advance
+/// whether anything will be written, so it always copies a borrowed string, but the `Cow`
+/// only becomes owned when the changes are committed.
+/// [`with_ascii_bytes_mut`](WithCheckedBytes::with_ascii_bytes_mut) always makes it owned.
+impl WithCheckedBytes for Cow<'_, str> {
+    fn with_checked_bytes_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+    {
+        let s = match self {
+            Cow::Borrowed(s) => *s,
+            Cow::Owned(s) => return s.with_checked_bytes_mut(f),
+        };
+        let (res, edited) = edit_shared(s, f)?;
+        if let Some(edited) = edited {
+            *self = Cow::Owned(edited);
+        }
+        Ok(res)
+    }
+
+    fn with_checked_bytes_in_place_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+    {
+        match self {
+            Cow::Borrowed(_) => self.with_checked_bytes_mut(f),
+            Cow::Owned(s) => s.with_checked_bytes_in_place_mut(f),
+        }
+    }
+
+    fn with_checked_bytes_in_mut<'a, R, F>(&'a mut self, scratch: &mut [u8], f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+    {
+        let s = match self {
+            Cow::Borrowed(s) => *s,
+            Cow::Owned(s) => return s.with_checked_bytes_in_mut(scratch, f),
+        };
+        let (res, edited) = edit_shared_in(s, scratch, f)?;
+        if let Some(edited) = edited {
+            *self = Cow::Owned(edited);
+        }
+        Ok(res)
+    }
+
+    fn checked_bytes_guard(&mut self) -> CheckedBytesGuard<'_> {
+        match self {
+            Cow::Borrowed(s) => {
+                let copy = String::from(*s);
+                CheckedBytesGuard::with_write_back(copy, move |edited| *self = Cow::Owned(edited))
+            }
+            Cow::Owned(s) => s.checked_bytes_guard(),
+        }
+    }
+
+    fn with_ascii_bytes_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut [AsciiByte]) -> R,
+    {
+        self.to_mut().with_ascii_bytes_mut(f)
+    }
+}
+
+macro_rules! impl_for_shared_pointer {
+    ($ptr:ident) => {
+        impl crate::sealed::Sealed for $ptr<str> {}
+
+        /// A string that is not shared is edited directly. Otherwise it is only copied if the
+        /// closure changes it and the result is valid, and the pointer is replaced with one
+        /// to the new string.
+        ///
+        /// [`checked_bytes_guard`](WithCheckedBytes::checked_bytes_guard) can't tell in advance
+        /// whether anything will be written, so it always copies a shared string, but the
+        /// pointer is only replaced when changes that modify the string are committed.
+        /// [`with_ascii_bytes_mut`](WithCheckedBytes::with_ascii_bytes_mut) always copies a
+        /// shared string.
+        impl WithCheckedBytes for $ptr<str> {
+            fn with_checked_bytes_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, Error>
+            where
+                F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+            {
+                if let Some(s) = $ptr::get_mut(self) {
+                    return s.with_checked_bytes_mut(f);
+                }
+                let (res, edited) = edit_shared(self, f)?;
+                if let Some(edited) = edited {
+                    *self = $ptr::from(edited);
+                }
+                Ok(res)
+            }
+
+            fn with_checked_bytes_in_place_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, Error>
+            where
+                F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+            {
+                match $ptr::get_mut(self) {
+                    Some(s) => s.with_checked_bytes_in_place_mut(f),
+                    None => self.with_checked_bytes_mut(f),
+                }
+            }
+
+            fn with_checked_bytes_in_mut<'a, R, F>(&'a mut self, scratch: &mut [u8], f: F) -> Result<R, Error>
+            where
+                F: for<'b> FnOnce(&'b mut MutableStringBytes) -> R,
+            {
+                if let Some(s) = $ptr::get_mut(self) {
+                    return s.with_checked_bytes_in_mut(scratch, f);
+                }
+                let (res, edited) = edit_shared_in(self, scratch, f)?;
+                if let Some(edited) = edited {
+                    *self = $ptr::from(edited);
+                }
+                Ok(res)
+            }
+
+            fn checked_bytes_guard(&mut self) -> CheckedBytesGuard<'_> {
+                if $ptr::get_mut(self).is_some() {
+                    return $ptr::get_mut(self).expect("string is not shared").checked_bytes_guard();
+                }
+                let copy = String::from(&**self);
+                CheckedBytesGuard::with_write_back(copy, move |edited| *self = $ptr::from(edited))
+            }
+
+            fn with_ascii_bytes_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, Error>
+            where
+                F: for<'b> FnOnce(&'b mut [AsciiByte]) -> R,
+            {
+                if $ptr::get_mut(self).is_none() {
+                    *self = $ptr::from(&**self);
+                }
+                $ptr::get_mut(self).expect("string was just copied").with_ascii_bytes_mut(f)
+            }
+        }
+    };
+}
+
+impl_for_shared_pointer!(Rc);
+impl_for_shared_pointer!(Arc);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DropPolicy;
+
+    #[test]
+    fn boxed_str() {
+        let mut my_string: Box<str> = "Hello".into();
+        my_string.with_checked_bytes_mut(|s| s.set(0, b'J')).unwrap();
+        assert_eq!(&*my_string, "Jello");
+    }
+
+    #[test]
+    fn cow_stays_borrowed() {
+        let original = "Hello";
+        let mut my_string = Cow::Borrowed(original);
+        let first = my_string.with_checked_bytes_mut(|s| s[0]).unwrap();
+        assert_eq!(first, b'H');
+        my_string.with_checked_bytes_mut(|s| s.set(0, 0xff)).unwrap_err();
+        assert!(matches!(my_string, Cow::Borrowed(_)));
+        my_string.with_checked_bytes_mut(|s| s.set(0, b'J')).unwrap();
+        assert!(matches!(my_string, Cow::Owned(_)));
+        assert_eq!(my_string, "Jello");
+        assert_eq!(original, "Hello");
+    }
+
+    #[test]
+    fn rc_copies_when_shared() {
+        let mut my_string: Rc<str> = Rc::from("Hello");
+        let other = Rc::clone(&my_string);
+        my_string.with_checked_bytes_mut(|s| s[0]).unwrap();
+        assert!(Rc::ptr_eq(&my_string, &other));
+        my_string.with_checked_bytes_mut(|s| s.set(0, b'J')).unwrap();
+        assert_eq!(&*my_string, "Jello");
+        assert_eq!(&*other, "Hello");
+        let unique = Rc::as_ptr(&my_string);
+        my_string.with_checked_bytes_mut(|s| s.set(1, b'a')).unwrap();
+        assert_eq!(Rc::as_ptr(&my_string), unique);
+        assert_eq!(&*my_string, "Jallo");
+    }
+
+    #[test]
+    fn arc_with_scratch_buffer() {
+        let mut my_string: Arc<str> = Arc::from("Hello");
+        let other = Arc::clone(&my_string);
+        my_string.with_checked_bytes_stack_mut::<8, _, _>(|s| s.set(0, b'J')).unwrap();
+        assert_eq!(&*my_string, "Jello");
+        assert_eq!(&*other, "Hello");
+        let mut guard = my_string.checked_bytes_guard();
+        guard.set(4, 0xff);
+        assert!(guard.commit().is_err());
+    }
+
+    #[test]
+    fn unchanged_edit_keeps_shared() {
+        let mut my_string: Rc<str> = Rc::from("Hello");
+        let other = Rc::clone(&my_string);
+        let ((), changes) = my_string.with_checked_bytes_tracked_mut(|s| s.set(0, b'H')).unwrap();
+        assert!(changes.is_empty());
+        assert!(Rc::ptr_eq(&my_string, &other));
+
+        let mut my_string: Arc<str> = Arc::from("Hello");
+        let other = Arc::clone(&my_string);
+        my_string.with_checked_bytes_mut(|s| s.write_at(1, b"ell")).unwrap();
+        assert!(Arc::ptr_eq(&my_string, &other));
+        my_string.with_checked_bytes_stack_mut::<8, _, _>(|s| s.set(4, b'o')).unwrap();
+        assert!(Arc::ptr_eq(&my_string, &other));
+
+        let mut my_string = Cow::Borrowed("Hello");
+        my_string.with_checked_bytes_mut(|s| s.set(0, b'H')).unwrap();
+        assert!(matches!(my_string, Cow::Borrowed(_)));
+        my_string.with_checked_bytes_stack_mut::<8, _, _>(|s| s.set(0, b'H')).unwrap();
+        assert!(matches!(my_string, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn guard_over_shared() {
+        let mut my_string: Rc<str> = Rc::from("Hello");
+        let other = Rc::clone(&my_string);
+        let mut guard = my_string.checked_bytes_guard();
+        guard.set(0, b'J');
+        guard.rollback();
+        assert!(Rc::ptr_eq(&my_string, &other));
+        let mut guard = my_string.checked_bytes_guard();
+        guard.set(0, b'J');
+        guard.commit().unwrap();
+        assert_eq!(&*my_string, "Jello");
+        assert_eq!(&*other, "Hello");
+
+        // Committing without modifying anything keeps the shared string
+        let other = Rc::clone(&my_string);
+        my_string.checked_bytes_guard().commit().unwrap();
+        assert!(Rc::ptr_eq(&my_string, &other));
+        let mut guard = my_string.checked_bytes_guard().with_drop_policy(DropPolicy::CommitIfValid);
+        guard.set(0, b'J');
+        drop(guard);
+        assert!(Rc::ptr_eq(&my_string, &other));
+
+        let mut my_string: Arc<str> = Arc::from("Hello");
+        let other = Arc::clone(&my_string);
+        my_string.checked_bytes_guard().commit().unwrap();
+        assert!(Arc::ptr_eq(&my_string, &other));
+
+        let mut my_string = Cow::Borrowed("Hello");
+        let mut guard = my_string.checked_bytes_guard();
+        guard.set(0, 0xff);
+        drop(guard);
+        assert!(matches!(my_string, Cow::Borrowed(_)));
+        my_string.checked_bytes_guard().commit().unwrap();
+        assert!(matches!(my_string, Cow::Borrowed(_)));
+        drop(my_string.checked_bytes_guard().with_drop_policy(DropPolicy::CommitIfValid));
+        assert!(matches!(my_string, Cow::Borrowed(_)));
+        let mut guard = my_string.checked_bytes_guard().with_drop_policy(DropPolicy::CommitIfValid);
+        guard.set(0, b'J');
+        drop(guard);
+        assert!(matches!(my_string, Cow::Owned(_)));
+        assert_eq!(my_string, "Jello");
+    }
+}
@@ -0,0 +1,450 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+#[cfg(feature = "unicode-width")]
+use unicode_width::UnicodeWidthChar;
+
+use crate::{Error, InvalidUtf8Error, MutableStringBytes, MutableStringVec};
+
+/// How the widths in a [`RecordLayout`] are measured
+///
+/// More units are available with some features, so matches need a wildcard arm.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Unit {
+    /// Each field occupies a fixed number of bytes.
+    #[default]
+    Bytes,
+    /// Each field occupies a fixed number of terminal columns, so its length in bytes depends
+    /// on its contents.
+    #[cfg(feature = "unicode-width")]
+    Columns,
+}
+
+impl Unit {
+    fn char_width(self, c: char) -> usize {
+        match self {
+            Self::Bytes => c.len_utf8(),
+            #[cfg(feature = "unicode-width")]
+            Self::Columns => c.width().unwrap_or(0),
+        }
+    }
+}
+
+/// Where a value sits within a field that is wider than it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Align {
+    #[default]
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FieldSpec {
+    name: String,
+    width: usize,
+    align: Align,
+    pad: u8,
+}
+
+/// Describes a string as a sequence of fixed-width fields
+///
+/// Fields are added in order with [`field`](Self::field), and the alignment and padding of the
+/// most recently added field can then be adjusted. Use the layout to edit a string with
+/// [`MutableStringBytes::record`] or [`MutableStringVec::record`].
+///
+/// ```
+/// use with_checked_bytes::{Align, RecordLayout, Unit, WithCheckedBytes};
+///
+/// let layout = RecordLayout::new(Unit::Bytes)
+///     .field("id", 6).aligned(Align::Right).padded(b'0')
+///     .field("name", 8);
+/// let mut line = layout.blank();
+/// line.with_checked_bytes_mut(|s| {
+///     let mut record = s.record(&layout);
+///     record.set("id", "42").unwrap();
+///     record.set("name", "Ada Lovelace").unwrap();
+/// }).unwrap();
+/// assert_eq!(line, "000042Ada Love");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordLayout {
+    unit: Unit,
+    fields: Vec<FieldSpec>,
+}
+
+impl RecordLayout {
+    /// Create a layout with no fields, measured in `unit`.
+    pub fn new(unit: Unit) -> Self {
+        Self {
+            unit,
+            fields: Vec::new(),
+        }
+    }
+
+    /// Add a field after the existing ones. It is left-aligned and padded with spaces.
+    pub fn field(mut self, name: impl Into<String>, width: usize) -> Self {
+        self.fields.push(FieldSpec {
+            name: name.into(),
+            width,
+            align: Align::Left,
+            pad: b' ',
+        });
+        self
+    }
+
+    /// Set the alignment of the most recently added field.
+    ///
+    /// Panics if no fields have been added.
+    pub fn aligned(mut self, align: Align) -> Self {
+        self.last_field().align = align;
+        self
+    }
+
+    /// Set the padding byte of the most recently added field.
+    ///
+    /// Panics if no fields have been added, or if `pad` is not an ASCII byte.
+    pub fn padded(mut self, pad: u8) -> Self {
+        assert!(pad.is_ascii(), "padding byte {:#04x} is not ASCII", pad);
+        self.last_field().pad = pad;
+        self
+    }
+
+    fn last_field(&mut self) -> &mut FieldSpec {
+        self.fields.last_mut().expect("layout has no fields")
+    }
+
+    /// How the field widths are measured.
+    pub fn unit(&self) -> Unit {
+        self.unit
+    }
+
+    /// The number of fields.
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Whether the layout has no fields.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// The total width of all fields.
+    pub fn width(&self) -> usize {
+        self.fields.iter().map(|f| f.width).sum()
+    }
+
+    /// The index of the field called `name`, if there is one.
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|f| f.name == name)
+    }
+
+    /// A record in which every field contains only padding.
+    pub fn blank(&self) -> String {
+        let bytes = self
+            .fields
+            .iter()
+            .flat_map(|f| core::iter::repeat_n(f.pad, f.width))
+            .collect();
+        // SAFETY: Padding bytes are always ASCII
+        unsafe { String::from_utf8_unchecked(bytes) }
+    }
+
+    /// The range of units from the start of the record covered by field `index`.
+    fn extent(&self, index: usize) -> Range<usize> {
+        let start: usize = self.fields[..index].iter().map(|f| f.width).sum();
+        start..start + self.fields[index].width
+    }
+
+    /// Find the bytes of `record` that hold field `index`.
+    fn locate(&self, record: &[u8], index: usize) -> Result<Range<usize>, Error> {
+        let extent = self.extent(index);
+        let range = match self.unit {
+            Unit::Bytes => Some(extent),
+            #[cfg(feature = "unicode-width")]
+            Unit::Columns => {
+                let text = core::str::from_utf8(record)
+                    .map_err(|e| Error::InvalidUtf8(InvalidUtf8Error::from_slice(record, e)))?;
+                column_range(text, self.unit, extent)
+            }
+        };
+        match range {
+            Some(range) if range.end <= record.len() => Ok(range),
+            _ => Err(Error::RecordTooShort {
+                field: index,
+                len: record.len(),
+            }),
+        }
+    }
+}
+
+/// Convert a range of units into a range of bytes, moving each end forwards to a character
+/// boundary. Returns `None` if `text` is too short.
+#[cfg(feature = "unicode-width")]
+fn column_range(text: &str, unit: Unit, units: Range<usize>) -> Option<Range<usize>> {
+    let mut position = 0;
+    let mut start = None;
+    for (index, c) in text.char_indices() {
+        if start.is_none() && position >= units.start {
+            start = Some(index);
+        }
+        if position >= units.end {
+            return Some(start?..index);
+        }
+        position += unit.char_width(c);
+    }
+    if position < units.end {
+        return None;
+    }
+    Some(start.unwrap_or(text.len())..text.len())
+}
+
+/// Identifies a field of a [`RecordLayout`], either by name or by index
+pub trait FieldKey {
+    /// The index of the field. Panics if there is no such field.
+    fn index_in(&self, layout: &RecordLayout) -> usize;
+}
+
+impl FieldKey for usize {
+    fn index_in(&self, layout: &RecordLayout) -> usize {
+        assert!(*self < layout.len(), "layout has no field {}", self);
+        *self
+    }
+}
+
+impl FieldKey for &str {
+    fn index_in(&self, layout: &RecordLayout) -> usize {
+        layout.position(self).unwrap_or_else(|| panic!("layout has no field named {:?}", self))
+    }
+}
+
+mod sealed {
+    use core::ops::Range;
+
+    use crate::Error;
+
+    /// A byte view that a [`Record`](super::Record) can edit
+    pub trait RecordBuffer {
+        fn bytes(&self) -> &[u8];
+        fn replace(&mut self, range: Range<usize>, with: &[u8]) -> Result<(), Error>;
+    }
+}
+
+use sealed::RecordBuffer;
+
+impl<'a> RecordBuffer for MutableStringBytes<'a> {
+    fn bytes(&self) -> &[u8] {
+        self
+    }
+
+    fn replace(&mut self, range: Range<usize>, with: &[u8]) -> Result<(), Error> {
+        if with.len() != range.len() {
+            return Err(Error::FieldLength {
+                needed: with.len(),
+                available: range.len(),
+            });
+        }
+        self.write_at(range.start, with);
+        Ok(())
+    }
+}
+
+impl<'a> RecordBuffer for MutableStringVec<'a> {
+    fn bytes(&self) -> &[u8] {
+        self
+    }
+
+    fn replace(&mut self, range: Range<usize>, with: &[u8]) -> Result<(), Error> {
+        self.splice(range, with.iter().copied());
+        Ok(())
+    }
+}
+
+/// A view for reading and writing the fields of a string described by a [`RecordLayout`]
+///
+/// Values are padded and aligned to fill their field. A value that is too wide is truncated,
+/// but never in the middle of a character. A wide character that doesn't fit is replaced by
+/// padding.
+///
+/// When the layout is measured in bytes every field has a fixed position. When it is measured
+/// in columns the fields are found by measuring the current contents, and a
+/// [`MutableStringBytes`] can only hold a new value that has the same length in bytes as the
+/// old one. Edit through a [`MutableStringVec`] to let the string grow and shrink.
+pub struct Record<'l, 'v, B> {
+    layout: &'l RecordLayout,
+    view: &'v mut B,
+}
+
+impl<'a> MutableStringBytes<'a> {
+    /// Edit the buffer as a record with the given layout.
+    pub fn record<'l>(&mut self, layout: &'l RecordLayout) -> Record<'l, '_, Self> {
+        Record { layout, view: self }
+    }
+}
+
+impl<'a> MutableStringVec<'a> {
+    /// Edit the buffer as a record with the given layout.
+    pub fn record<'l>(&mut self, layout: &'l RecordLayout) -> Record<'l, '_, Self> {
+        Record { layout, view: self }
+    }
+}
+
+impl<'l, 'v, B: RecordBuffer> Record<'l, 'v, B> {
+    /// The layout of the record.
+    pub fn layout(&self) -> &'l RecordLayout {
+        self.layout
+    }
+
+    /// The value of a field, without its padding.
+    ///
+    /// Every padding character at the padded end of the field is removed, including any that
+    /// belong to the value. So a value that starts or ends with the padding character where it
+    /// meets the padding doesn't read back as it was set: in a right-aligned field padded with
+    /// `'0'`, both `"007"` and `"7"` read back as `"7"`, and `"0"` reads back as `""`.
+    ///
+    /// Fails if the record is too short to contain the field, if the field doesn't currently
+    /// contain valid UTF-8, or if the layout is measured in columns and the record up to the
+    /// field isn't valid UTF-8. Panics if there is no such field.
+    pub fn get<K: FieldKey>(&self, field: K) -> Result<&str, Error> {
+        let index = field.index_in(self.layout);
+        let spec = &self.layout.fields[index];
+        let record = self.view.bytes();
+        let slot = &record[self.layout.locate(record, index)?];
+        let value = core::str::from_utf8(slot)
+            .map_err(|e| Error::InvalidUtf8(InvalidUtf8Error::from_slice(slot, e)))?;
+        let pad = char::from(spec.pad);
+        Ok(match spec.align {
+            Align::Left => value.trim_end_matches(pad),
+            Align::Right => value.trim_start_matches(pad),
+            Align::Center => value.trim_matches(pad),
+        })
+    }
+
+    /// Replace the value of a field, padding or truncating it to fit.
+    ///
+    /// Fails if the record is too short to contain the field. Panics if there is no such field.
+    pub fn set<K: FieldKey>(&mut self, field: K, value: &str) -> Result<(), Error> {
+        let index = field.index_in(self.layout);
+        let spec = &self.layout.fields[index];
+        let range = self.layout.locate(self.view.bytes(), index)?;
+
+        let mut used = 0;
+        let mut len = 0;
+        for c in value.chars() {
+            let width = self.layout.unit.char_width(c);
+            if used + width > spec.width {
+                break;
+            }
+            used += width;
+            len += c.len_utf8();
+        }
+        let padding = spec.width - used;
+        let before = match spec.align {
+            Align::Left => 0,
+            Align::Right => padding,
+            Align::Center => padding / 2,
+        };
+        let mut formatted = Vec::with_capacity(len + padding);
+        formatted.resize(before, spec.pad);
+        formatted.extend_from_slice(&value.as_bytes()[..len]);
+        formatted.resize(len + padding, spec.pad);
+        self.view.replace(range, &formatted)
+    }
+
+    /// Fill a field with padding.
+    pub fn clear<K: FieldKey>(&mut self, field: K) -> Result<(), Error> {
+        self.set(field, "")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WithCheckedBytes;
+
+    fn layout() -> RecordLayout {
+        RecordLayout::new(Unit::Bytes)
+            .field("code", 4).aligned(Align::Right).padded(b'0')
+            .field("city", 7)
+            .field("flag", 3).aligned(Align::Center).padded(b'*')
+    }
+
+    #[test]
+    fn bytes_layout() {
+        let layout = layout();
+        let mut line = String::from("0012Zürich***");
+        line.with_checked_bytes_mut(|s| {
+            let mut record = s.record(&layout);
+            assert_eq!(record.get("code").unwrap(), "12");
+            assert_eq!(record.get(1).unwrap(), "Zürich");
+            record.set("city", "Lugano–Paradiso").unwrap();
+            record.set("flag", "Y").unwrap();
+            record.set("code", "7").unwrap();
+        }).unwrap();
+        // The dash would cross the end of the field, so it is left out with the rest
+        assert_eq!(line, "0007Lugano *Y*");
+    }
+
+    #[test]
+    #[should_panic(expected = "layout has no field named \"zip\"")]
+    fn unknown_field() {
+        let layout = layout();
+        let mut line = layout.blank();
+        line.with_checked_bytes_mut(|s| s.record(&layout).get("zip").map(|_| ())).unwrap().unwrap();
+    }
+
+    #[test]
+    fn padding_in_values() {
+        let layout = RecordLayout::new(Unit::Bytes)
+            .field("n", 4).aligned(Align::Right).padded(b'0')
+            .field("tag", 4).padded(b'-');
+        let mut line = layout.blank();
+        line.with_checked_bytes_mut(|s| {
+            let mut record = s.record(&layout);
+            record.set("n", "0").unwrap();
+            record.set("tag", "a-b-").unwrap();
+            assert_eq!(record.get("n").unwrap(), "");
+            assert_eq!(record.get("tag").unwrap(), "a-b");
+            record.set("n", "1070").unwrap();
+            assert_eq!(record.get("n").unwrap(), "1070");
+        }).unwrap();
+        assert_eq!(line, "1070a-b-");
+    }
+
+    #[test]
+    fn truncated_record() {
+        let layout = layout();
+        let mut line = String::from("0012Zürich*");
+        line.with_checked_bytes_mut(|s| {
+            let mut record = s.record(&layout);
+            assert_eq!(record.get("city").unwrap(), "Zürich");
+            assert!(matches!(record.get("flag"), Err(Error::RecordTooShort { field: 2, len: 12 })));
+            assert!(matches!(record.set("flag", "N"), Err(Error::RecordTooShort { field: 2, len: 12 })));
+            record.set("code", "9").unwrap();
+        }).unwrap();
+        assert_eq!(line, "0009Zürich*");
+    }
+
+    #[cfg(feature = "unicode-width")]
+    #[test]
+    fn columns_layout() {
+        use crate::WithCheckedVec;
+
+        let layout = RecordLayout::new(Unit::Columns)
+            .field("mode", 6)
+            .field("file", 6).aligned(Align::Right);
+        let mut status = layout.blank();
+        status.with_checked_vec_mut(|s| {
+            let mut record = s.record(&layout);
+            record.set("file", "日本語.txt").unwrap();
+            record.set("mode", "ÉDIT").unwrap();
+            assert_eq!(record.get("file").unwrap(), "日本語");
+        }).unwrap();
+        assert_eq!(status, "ÉDIT  日本語");
+
+        let err = status.with_checked_bytes_mut(|s| s.record(&layout).set("mode", "EDIT")).unwrap();
+        assert!(matches!(err, Err(Error::FieldLength { needed: 6, available: 7 })));
+    }
+}
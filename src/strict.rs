@@ -0,0 +1,142 @@
+use alloc::vec::Vec;
+use core::ops::{Deref, Range, RangeBounds};
+use core::panic::Location;
+
+use crate::bytes::resolve_range;
+use crate::{validate, Error, MutableStringBytes};
+
+/// Where and when a write was made through a [`StrictStringBytes`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteProvenance {
+    /// The bytes that were written
+    pub range: Range<usize>,
+    /// How many writes came before this one
+    pub order: usize,
+    /// The source location of the call that made the write
+    pub location: &'static Location<'static>,
+}
+
+/// A view used by [`with_checked_bytes_strict_mut`](crate::WithCheckedBytes::with_checked_bytes_strict_mut)
+/// that records every write
+///
+/// The buffer can be read through `Deref`, but can only be modified with the methods here, each
+/// of which remembers which bytes it wrote and where it was called from. If the edit is not
+/// valid UTF-8, this is used to find the write responsible.
+pub struct StrictStringBytes<'v, 'a> {
+    view: &'v mut MutableStringBytes<'a>,
+    writes: Vec<WriteProvenance>,
+}
+
+impl<'v, 'a> StrictStringBytes<'v, 'a> {
+    pub(crate) fn new(view: &'v mut MutableStringBytes<'a>) -> Self {
+        Self {
+            view,
+            writes: Vec::new(),
+        }
+    }
+
+    /// Overwrite the byte at `index`.
+    ///
+    /// Panics if `index` is out of bounds.
+    #[track_caller]
+    pub fn set(&mut self, index: usize, byte: u8) {
+        self.range_mut(index..index + 1)[0] = byte;
+    }
+
+    /// Overwrite the bytes starting at `offset` with the contents of `bytes`.
+    ///
+    /// Panics if the write would extend past the end of the buffer.
+    #[track_caller]
+    pub fn write_at(&mut self, offset: usize, bytes: &[u8]) {
+        self.range_mut(offset..offset + bytes.len()).copy_from_slice(bytes);
+    }
+
+    /// Get mutable access to a subrange of the buffer.
+    ///
+    /// The whole range is recorded as written by this call. Panics if the range is out of bounds.
+    #[track_caller]
+    pub fn range_mut<R: RangeBounds<usize>>(&mut self, range: R) -> &mut [u8] {
+        let range = resolve_range(range, self.view.len());
+        if !range.is_empty() {
+            self.writes.push(WriteProvenance {
+                range: range.clone(),
+                order: self.writes.len(),
+                location: Location::caller(),
+            });
+        }
+        self.view.range_mut(range)
+    }
+
+    /// Every write made so far, in order.
+    pub fn writes(&self) -> &[WriteProvenance] {
+        &self.writes
+    }
+
+    /// Throw away every modification made so far, along with the record of writes.
+    pub fn discard(&mut self) {
+        self.view.discard();
+        self.writes.clear();
+    }
+
+    /// If the buffer is not valid UTF-8, describe the first invalid sequence and the last write
+    /// that touched it.
+    pub(crate) fn blame(&self) -> Option<Error> {
+        let error = self.view.check().err()?;
+        let position = error.valid_up_to();
+        let write = self.writes.iter().rev().find(|w| {
+            let affected = validate::widen(self.view, w.range.clone(), 0);
+            affected.contains(&position)
+        });
+        Some(match write {
+            Some(write) => Error::InvalidWrite {
+                error,
+                write: write.clone(),
+            },
+            None => Error::InvalidUtf8(error),
+        })
+    }
+}
+
+impl<'v, 'a> Deref for StrictStringBytes<'v, 'a> {
+    type Target = MutableStringBytes<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        self.view
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, WithCheckedBytes};
+
+    #[test]
+    fn blames_the_write() {
+        let mut my_string = String::from("price: 10€");
+        let err = my_string.with_checked_bytes_strict_mut(|s| {
+            s.set(0, b'P');
+            s.write_at(7, b"12");
+            s.set(9, b'$');
+            s.set(8, b'5');
+        }).unwrap_err();
+        let Error::InvalidWrite { error, write } = err else {
+            panic!("unexpected error {:?}", err);
+        };
+        // Overwriting the start of "€" leaves its continuation bytes invalid
+        assert_eq!(error.valid_up_to(), 10);
+        assert_eq!(write.range, 9..10);
+        assert_eq!(write.order, 2);
+        assert_eq!(write.location.file(), file!());
+        assert_eq!(my_string, "price: 10€");
+    }
+
+    #[test]
+    fn valid_edit() {
+        let mut my_string = String::from("price: 10€");
+        my_string.with_checked_bytes_strict_mut(|s| {
+            s.write_at(9, "£".as_bytes());
+            s.write_at(11, b"!");
+            assert_eq!(s.writes().len(), 2);
+        }).unwrap();
+        assert_eq!(my_string, "price: 10£!");
+    }
+}
@@ -0,0 +1,209 @@
+//! Incremental UTF-8 validation of edited buffers.
+//!
+//! Every buffer handed out for editing starts as a copy of a valid `str`. If we know which
+//! bytes changed, only those regions (widened out to the surrounding character boundaries)
+//! need to be validated to know whether the whole buffer is still valid UTF-8.
+
+use core::ops::Range;
+use core::str::Utf8Error;
+
+/// Size of the blocks compared at once when searching for changed bytes.
+const CHUNK: usize = 64;
+
+pub(crate) fn is_continuation(byte: u8) -> bool {
+    byte & 0xc0 == 0x80
+}
+
+/// Check that `buf` is valid UTF-8, given that it was valid before the bytes in `changed` were
+/// modified.
+///
+/// `changed` must be sorted and non-overlapping. Empty ranges are allowed and mark a point
+/// where bytes were removed from the buffer. On failure the error describes the whole buffer,
+/// exactly as [`core::str::from_utf8`] would.
+pub(crate) fn validate_changed<I>(buf: &[u8], changed: I) -> Result<(), Utf8Error>
+where
+    I: IntoIterator<Item = Range<usize>>,
+{
+    let mut pending: Option<Range<usize>> = None;
+    for range in changed {
+        let floor = pending.as_ref().map_or(0, |p| p.end);
+        let Range { start, end } = widen(buf, range, floor);
+        pending = match pending {
+            Some(p) if start <= p.end => Some(p.start..end.max(p.end)),
+            Some(p) => {
+                check(buf, p)?;
+                Some(start..end)
+            }
+            None => Some(start..end),
+        };
+    }
+    match pending {
+        Some(p) => check(buf, p),
+        None => Ok(()),
+    }
+}
+
+/// Extend a modified `range` out to the region whose validity it can affect: back to the start
+/// of the character before it (but not below `floor`), and forwards past any continuation bytes.
+pub(crate) fn widen(buf: &[u8], range: Range<usize>, floor: usize) -> Range<usize> {
+    let mut start = range.start.max(floor);
+    // Back up to the first byte of the character that was overlapping the change
+    while start > floor && is_continuation(buf[start - 1]) {
+        start -= 1;
+    }
+    if start > floor {
+        start -= 1;
+    }
+    let mut end = range.end.max(start);
+    while end < buf.len() && is_continuation(buf[end]) {
+        end += 1;
+    }
+    start..end
+}
+
+fn check(buf: &[u8], range: Range<usize>) -> Result<(), Utf8Error> {
+    if is_utf8(&buf[range]) {
+        Ok(())
+    } else {
+        // Report the error relative to the whole buffer rather than the region
+        core::str::from_utf8(buf).map(|_| ())
+    }
+}
+
+/// Check whether `bytes` is valid UTF-8 as quickly as possible, without details of any error.
+///
+/// With the `simd` feature this uses SIMD instructions where the CPU supports them. When `std`
+/// is enabled the best implementation is chosen at runtime, otherwise it depends on the target
+/// features enabled at compile time.
+#[cfg(feature = "simd")]
+pub(crate) fn is_utf8(bytes: &[u8]) -> bool {
+    simdutf8::basic::from_utf8(bytes).is_ok()
+}
+
+#[cfg(not(feature = "simd"))]
+pub(crate) fn is_utf8(bytes: &[u8]) -> bool {
+    core::str::from_utf8(bytes).is_ok()
+}
+
+/// Iterator over the maximal runs of bytes that differ between two buffers of equal length.
+pub(crate) struct ChangedRuns<'x> {
+    original: &'x [u8],
+    edited: &'x [u8],
+    pos: usize,
+    end: usize,
+}
+
+/// Find the runs of bytes within `within` that differ between `original` and `edited`.
+pub(crate) fn changed_runs<'x>(original: &'x [u8], edited: &'x [u8], within: Range<usize>) -> ChangedRuns<'x> {
+    debug_assert_eq!(original.len(), edited.len());
+    ChangedRuns {
+        original,
+        edited,
+        pos: within.start,
+        end: within.end,
+    }
+}
+
+impl<'x> Iterator for ChangedRuns<'x> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos < self.end {
+            let chunk_end = (self.pos + CHUNK).min(self.end);
+            if self.original[self.pos..chunk_end] != self.edited[self.pos..chunk_end] {
+                break;
+            }
+            self.pos = chunk_end;
+        }
+        while self.pos < self.end && self.original[self.pos] == self.edited[self.pos] {
+            self.pos += 1;
+        }
+        if self.pos >= self.end {
+            return None;
+        }
+        let start = self.pos;
+        while self.pos < self.end && self.original[self.pos] != self.edited[self.pos] {
+            self.pos += 1;
+        }
+        Some(start..self.pos)
+    }
+}
+
+/// Find the single region of `edited` that differs from `original` when the lengths may differ.
+///
+/// The returned range may be empty if bytes were only removed.
+#[cfg(feature = "alloc")]
+pub(crate) fn changed_span(original: &[u8], edited: &[u8]) -> Range<usize> {
+    let prefix = original.iter().zip(edited).take_while(|(a, b)| a == b).count();
+    let max_suffix = original.len().min(edited.len()) - prefix;
+    let suffix = original
+        .iter()
+        .rev()
+        .zip(edited.iter().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    prefix..edited.len() - suffix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit_and_check(original: &str, edit: impl FnOnce(&mut Vec<u8>)) {
+        let mut edited = original.as_bytes().to_vec();
+        edit(&mut edited);
+        let runs: Vec<_> = changed_runs(original.as_bytes(), &edited, 0..edited.len()).collect();
+        assert_eq!(
+            validate_changed(&edited, runs),
+            std::str::from_utf8(&edited).map(|_| ()),
+            "edited: {:x?}",
+            edited
+        );
+    }
+
+    #[test]
+    fn matches_full_validation() {
+        let original = "aé€𝄞 plain ascii and more é€𝄞";
+        for i in 0..original.len() {
+            for byte in [b'x', 0x80, 0xa9, 0xc3, 0xe2, 0xf0, 0xff] {
+                edit_and_check(original, |v| v[i] = byte);
+            }
+        }
+    }
+
+    #[test]
+    fn adjacent_regions_merge() {
+        // Replace "é" with "ü" one byte at a time in separate runs
+        edit_and_check("xéx", |v| {
+            v[1] = 0xc3;
+            v[2] = 0xbc;
+        });
+        edit_and_check("ab", |v| {
+            v[0] = 0xc3;
+            v[1] = 0xa9;
+        });
+    }
+
+    #[test]
+    fn large_buffer() {
+        // Long enough for the SIMD validator to work in blocks
+        let original = "é€𝄞 ".repeat(10_000);
+        for i in [0, 1, 4096, original.len() - 1] {
+            for byte in [b'x', 0x80, 0xf0] {
+                edit_and_check(&original, |v| {
+                    v[i] = byte;
+                    v[original.len() / 2] = b'!';
+                });
+            }
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn span_of_removal() {
+        let span = changed_span("né".as_bytes(), &"né".as_bytes()[..2]);
+        assert_eq!(span, 2..2);
+        assert!(validate_changed(&"né".as_bytes()[..2], Some(span)).is_err());
+    }
+}
@@ -0,0 +1,160 @@
+//! Rules that an edited string must follow in addition to being valid UTF-8
+//!
+//! See `WithCheckedBytes::with_validated_bytes_mut`, which needs the `alloc` feature.
+
+use crate::Rejection;
+
+/// A rule that an edited string must follow before it is committed
+///
+/// Validators are only run on strings that are already known to be valid UTF-8. Any closure
+/// taking a `&str` and returning `Result<(), Rejection>` is a validator, and validators can
+/// be combined with [`and`](Self::and) and [`or`](Self::or).
+pub trait Validator {
+    /// Check `s`, describing the first problem found if it breaks the rule.
+    fn validate(&self, s: &str) -> Result<(), Rejection>;
+
+    /// A validator that requires both `self` and `other` to accept the string.
+    fn and<V: Validator>(self, other: V) -> And<Self, V>
+    where
+        Self: Sized,
+    {
+        And(self, other)
+    }
+
+    /// A validator that requires either `self` or `other` to accept the string.
+    fn or<V: Validator>(self, other: V) -> Or<Self, V>
+    where
+        Self: Sized,
+    {
+        Or(self, other)
+    }
+}
+
+impl<F> Validator for F
+where
+    F: Fn(&str) -> Result<(), Rejection>,
+{
+    fn validate(&self, s: &str) -> Result<(), Rejection> {
+        self(s)
+    }
+}
+
+/// Accepts a string if both validators do; see [`Validator::and`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct And<A, B>(pub A, pub B);
+
+impl<A: Validator, B: Validator> Validator for And<A, B> {
+    fn validate(&self, s: &str) -> Result<(), Rejection> {
+        self.0.validate(s)?;
+        self.1.validate(s)
+    }
+}
+
+/// Accepts a string if either validator does; see [`Validator::or`]
+///
+/// If both reject it, the rejection that occurs later in the string is reported.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Or<A, B>(pub A, pub B);
+
+impl<A: Validator, B: Validator> Validator for Or<A, B> {
+    fn validate(&self, s: &str) -> Result<(), Rejection> {
+        let first = match self.0.validate(s) {
+            Ok(()) => return Ok(()),
+            Err(r) => r,
+        };
+        match self.1.validate(s) {
+            Ok(()) => Ok(()),
+            Err(second) if second.index > first.index => Err(second),
+            Err(_) => Err(first),
+        }
+    }
+}
+
+/// Reject the first character of `s` that doesn't satisfy `allowed`.
+fn reject_unless(s: &str, reason: &'static str, allowed: impl Fn(usize, char) -> bool) -> Result<(), Rejection> {
+    match s.char_indices().find(|&(i, c)| !allowed(i, c)) {
+        Some((index, _)) => Err(Rejection { index, reason }),
+        None => Ok(()),
+    }
+}
+
+/// Rejects strings containing a NUL character
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoNul;
+
+impl Validator for NoNul {
+    fn validate(&self, s: &str) -> Result<(), Rejection> {
+        match s.bytes().position(|b| b == 0) {
+            Some(index) => Err(Rejection { index, reason: "contains a NUL character" }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Rejects strings containing any control character, including tabs and line breaks
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoControl;
+
+impl Validator for NoControl {
+    fn validate(&self, s: &str) -> Result<(), Rejection> {
+        reject_unless(s, "contains a control character", |_, c| !c.is_control())
+    }
+}
+
+/// Only accepts printable ASCII characters, from space to `~`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrintableAscii;
+
+impl Validator for PrintableAscii {
+    fn validate(&self, s: &str) -> Result<(), Rejection> {
+        reject_unless(s, "contains a character that is not printable ASCII", |_, c| {
+            c == ' ' || c.is_ascii_graphic()
+        })
+    }
+}
+
+/// Only accepts ASCII identifiers: a letter or underscore followed by letters, digits and
+/// underscores
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Identifier;
+
+impl Validator for Identifier {
+    fn validate(&self, s: &str) -> Result<(), Rejection> {
+        if s.is_empty() {
+            return Err(Rejection { index: 0, reason: "identifier is empty" });
+        }
+        reject_unless(s, "not a valid identifier character", |i, c| {
+            c == '_' || if i == 0 { c.is_ascii_alphabetic() } else { c.is_ascii_alphanumeric() }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_validators() {
+        assert_eq!(NoNul.validate("a\0b"), Err(Rejection { index: 1, reason: "contains a NUL character" }));
+        assert!(NoControl.validate("tab\there").is_err());
+        assert!(PrintableAscii.validate("héllo").is_err());
+        assert!(PrintableAscii.validate("Hello, world!").is_ok());
+        assert!(Identifier.validate("_snake_case2").is_ok());
+        assert_eq!(Identifier.validate("2fast").map_err(|r| r.index), Err(0));
+        assert_eq!(Identifier.validate("").map_err(|r| r.index), Err(0));
+    }
+
+    #[test]
+    fn combinators() {
+        let no_spaces = |s: &str| match s.find(' ') {
+            Some(index) => Err(Rejection { index, reason: "contains a space" }),
+            None => Ok(()),
+        };
+        let v = PrintableAscii.and(no_spaces);
+        assert!(v.validate("a-b").is_ok());
+        assert_eq!(v.validate("a b").map_err(|r| r.reason), Err("contains a space"));
+        let v = Identifier.or(NoNul.and(no_spaces));
+        assert!(v.validate("a-b").is_ok());
+        assert_eq!(v.validate("a- b").map_err(|r| r.index), Err(2));
+    }
+}
@@ -0,0 +1,257 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::{Deref, DerefMut, RangeBounds};
+
+use crate::{validate, Error, InvalidUtf8Error};
+
+/// Extension trait for safely editing a `String` as a growable byte vector
+pub trait WithCheckedVec {
+    /// Edit a `String` as if it were a `Vec<u8>`.
+    ///
+    /// This works like [`WithCheckedBytes::with_checked_bytes_mut`](crate::WithCheckedBytes::with_checked_bytes_mut)
+    /// except that the view passed to the closure can also change length, using
+    /// `Vec`-like methods such as `push`, `insert`, `remove`, `truncate` and `splice`.
+    ///
+    /// If the buffer doesn't contain valid UTF-8 when the closure returns, the original
+    /// string will not be modified and an error will be returned. Otherwise the string
+    /// takes on the buffer's contents, including its new length.
+    fn with_checked_vec_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut MutableStringVec) -> R;
+
+    /// Edit a `String` as if it were a `Vec<u8>`, repairing any invalid UTF-8 instead of
+    /// rejecting the edit.
+    ///
+    /// After the closure returns, each invalid sequence is replaced with U+FFFD in the same
+    /// way as [`String::from_utf8_lossy`] and the result is always committed. The closure's
+    /// return value is passed back along with the number of replacements made.
+    fn with_checked_vec_lossy_mut<'a, R, F>(&'a mut self, f: F) -> (R, usize)
+    where
+        F: for<'b> FnOnce(&'b mut MutableStringVec) -> R,
+    {
+        self.with_checked_vec_mut(|s| {
+            let res = f(s);
+            let replaced = s.replace_invalid();
+            (res, replaced)
+        })
+        .expect("repaired bytes are valid UTF-8")
+    }
+}
+
+impl WithCheckedVec for String {
+    fn with_checked_vec_mut<'a, R, F>(&'a mut self, f: F) -> Result<R, Error>
+    where
+        F: for<'b> FnOnce(&'b mut MutableStringVec) -> R,
+    {
+        let (res, edited) = edit(self, f)?;
+        if let Some((edited, _)) = edited {
+            *self = edited;
+        }
+        Ok(res)
+    }
+}
+
+/// Run `f` over a growable view of `s`.
+///
+/// If anything was written and the result is valid UTF-8, it is returned along with the
+/// position of the first character that differs from `s`. Everything before that position
+/// is unchanged, so types that can't adopt the new `String` directly only need to truncate
+/// to it and append the rest.
+pub(crate) fn edit<R, F>(s: &str, f: F) -> Result<(R, Option<(String, usize)>), Error>
+where
+    F: for<'b> FnOnce(&'b mut MutableStringVec) -> R,
+{
+    let mut target = MutableStringVec::new(s.as_bytes());
+    let res = f(&mut target);
+    let Some(v) = target.edited else {
+        return Ok((res, None));
+    };
+    let changed = validate::changed_span(s.as_bytes(), &v);
+    if let Err(e) = validate::validate_changed(&v, Some(changed.clone())) {
+        return Err(Error::InvalidUtf8(InvalidUtf8Error::new(v, e)));
+    }
+    let mut start = changed.start;
+    while !s.is_char_boundary(start) {
+        start -= 1;
+    }
+    // SAFETY: We just proved that the new content is valid UTF-8
+    Ok((res, Some((unsafe { String::from_utf8_unchecked(v) }, start))))
+}
+
+/// Growable view into a string's content expressed as bytes
+///
+/// The string is only copied once the view is first modified.
+pub struct MutableStringVec<'a> {
+    original: &'a [u8],
+    /// The modified copy, if anything has been written
+    edited: Option<Vec<u8>>,
+}
+
+impl<'a> MutableStringVec<'a> {
+    fn new(original: &'a [u8]) -> Self {
+        Self {
+            original,
+            edited: None,
+        }
+    }
+
+    /// Get mutable access to the underlying vector, copying the original string if necessary.
+    pub fn to_mut(&mut self) -> &mut Vec<u8> {
+        self.edited.get_or_insert_with(|| self.original.to_vec())
+    }
+
+    /// Append a byte to the end of the buffer.
+    pub fn push(&mut self, byte: u8) {
+        self.to_mut().push(byte);
+    }
+
+    /// Remove the last byte from the buffer and return it, or `None` if it is empty.
+    pub fn pop(&mut self) -> Option<u8> {
+        if self.is_empty() {
+            return None;
+        }
+        self.to_mut().pop()
+    }
+
+    /// Insert a byte at position `index`, shifting all bytes after it to the right.
+    ///
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, byte: u8) {
+        self.to_mut().insert(index, byte);
+    }
+
+    /// Remove and return the byte at position `index`, shifting all bytes after it to the left.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> u8 {
+        self.to_mut().remove(index)
+    }
+
+    /// Shorten the buffer to `len` bytes. Has no effect if `len` is not less than the current length.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len() {
+            self.to_mut().truncate(len);
+        }
+    }
+
+    /// Remove all bytes from the buffer.
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Append all bytes in `other` to the end of the buffer.
+    pub fn extend_from_slice(&mut self, other: &[u8]) {
+        self.to_mut().extend_from_slice(other);
+    }
+
+    /// Replace the bytes in `range` with `replace_with`, returning the removed bytes.
+    ///
+    /// This behaves like [`Vec::splice`]; the buffer may grow or shrink.
+    pub fn splice<R, I>(&mut self, range: R, replace_with: I) -> alloc::vec::Splice<'_, I::IntoIter>
+    where
+        R: RangeBounds<usize>,
+        I: IntoIterator<Item = u8>,
+    {
+        self.to_mut().splice(range, replace_with)
+    }
+
+    /// Replace each invalid UTF-8 sequence with U+FFFD REPLACEMENT CHARACTER, returning the
+    /// number of replacements made.
+    pub fn replace_invalid(&mut self) -> usize {
+        if validate::is_utf8(self) {
+            return 0;
+        }
+        let mut repaired = Vec::with_capacity(self.len());
+        let mut replaced = 0;
+        for chunk in self.utf8_chunks() {
+            repaired.extend_from_slice(chunk.valid().as_bytes());
+            if !chunk.invalid().is_empty() {
+                repaired.extend_from_slice(char::REPLACEMENT_CHARACTER.encode_utf8(&mut [0; 4]).as_bytes());
+                replaced += 1;
+            }
+        }
+        *self.to_mut() = repaired;
+        replaced
+    }
+}
+
+impl<'a> Extend<u8> for MutableStringVec<'a> {
+    fn extend<I: IntoIterator<Item = u8>>(&mut self, iter: I) {
+        self.to_mut().extend(iter);
+    }
+}
+
+impl<'a, 'b> Extend<&'b u8> for MutableStringVec<'a> {
+    fn extend<I: IntoIterator<Item = &'b u8>>(&mut self, iter: I) {
+        self.to_mut().extend(iter);
+    }
+}
+
+impl<'a> Deref for MutableStringVec<'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.edited.as_deref().unwrap_or(self.original)
+    }
+}
+
+impl<'a> DerefMut for MutableStringVec<'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.to_mut().as_mut_slice()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_and_insert() {
+        let mut my_string = "ello".to_owned();
+        my_string.with_checked_vec_mut(|s| {
+            s.insert(0, b'H');
+            s.push(b'!');
+        }).unwrap();
+        assert_eq!(my_string, "Hello!");
+    }
+
+    #[test]
+    fn splice_multibyte() {
+        let mut my_string = "Hello world".to_owned();
+        my_string.with_checked_vec_mut(|s| {
+            s.splice(6..11, "wörld".bytes());
+        }).unwrap();
+        assert_eq!(my_string, "Hello wörld");
+    }
+
+    #[test]
+    fn truncate_mid_char_bad() {
+        let mut my_string = "né".to_owned();
+        my_string.with_checked_vec_mut(|s| {
+            s.truncate(2);
+        }).unwrap_err();
+        assert_eq!(my_string, "né");
+    }
+
+    #[test]
+    fn lossy_edit() {
+        let mut my_string = "né".to_owned();
+        let (_, replaced) = my_string.with_checked_vec_lossy_mut(|s| {
+            s.truncate(2);
+            s.insert(0, 0xff);
+        });
+        assert_eq!(replaced, 2);
+        assert_eq!(my_string, "\u{fffd}n\u{fffd}");
+    }
+
+    #[test]
+    fn read_only_does_not_copy() {
+        let mut my_string = "Hello".to_owned();
+        let first = my_string.with_checked_vec_mut(|s| {
+            s.truncate(10);
+            assert!(s.edited.is_none());
+            s[0]
+        }).unwrap();
+        assert_eq!(first, b'H');
+    }
+}